@@ -1,7 +1,7 @@
 use bevy::prelude::Entity;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use phusis::{
-    body::Body,
+    body::{Body, BodyType},
     shape::{Circle, Shape, AABB},
     world::{broad::BroadPhase, PhysicsWorld},
     QuadTree,
@@ -20,7 +20,7 @@ fn quad_tree_bench(c: &mut Criterion) {
                 black_box(1f32),
                 Shape::Circle(Circle::new(8f32)),
                 Vec2::new(1f32, 1f32),
-                black_box(false),
+                black_box(BodyType::Dynamic),
                 black_box(false),
                 Entity::from_raw(0),
             ))
@@ -38,7 +38,7 @@ fn quad_tree_bench(c: &mut Criterion) {
                     black_box(1f32),
                     Shape::Circle(Circle::new(8f32)),
                     Vec2::new(1f32, 1f32),
-                    black_box(false),
+                    black_box(BodyType::Dynamic),
                     black_box(false),
                     Entity::from_raw(0),
                 ));
@@ -66,7 +66,7 @@ fn quad_tree_bench(c: &mut Criterion) {
                 1f32,
                 Shape::Circle(Circle::new(10f32)),
                 Vec2::new(x, y),
-                false,
+                BodyType::Dynamic,
                 false,
                 Entity::from_raw(0),
             ));
@@ -89,7 +89,7 @@ fn quad_tree_bench(c: &mut Criterion) {
                 1f32,
                 Shape::Circle(Circle::new(10f32)),
                 Vec2::new(x, y),
-                false,
+                BodyType::Dynamic,
                 false,
                 Entity::from_raw(0),
             ));
@@ -111,7 +111,7 @@ fn quad_tree_bench(c: &mut Criterion) {
                 1f32,
                 Shape::Circle(Circle::new(10f32)),
                 Vec2::new(x, y),
-                false,
+                BodyType::Dynamic,
                 false,
                 Entity::from_raw(0),
             ));
@@ -134,7 +134,7 @@ fn quad_tree_bench(c: &mut Criterion) {
                 1f32,
                 Shape::Circle(Circle::new(10f32)),
                 Vec2::new(x, y),
-                false,
+                BodyType::Dynamic,
                 false,
                 Entity::from_raw(0),
             ));
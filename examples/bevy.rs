@@ -3,7 +3,8 @@ use bevy::{
     prelude::*,
 };
 use phusis::{
-    bevy::{Collider, PhusisBevyPlugin},
+    bevy::{BodyType, Collider, PhusisBevyPlugin},
+    body::{DEFAULT_FRICTION, DEFAULT_STATIC_FRICTION},
     shape::{Circle, Shape},
 };
 use rand::prelude::*;
@@ -23,11 +24,13 @@ fn add_bodies(mut commands: Commands) {
         commands
             .spawn(Transform::from_xyz(x, y, 1.0))
             .insert(Collider {
-                mass:         1.0,
-                shape:        Shape::Circle(Circle::new(radius)),
-                constitution: 1.0,
-                fixed:        false,
-                sensor:       false,
+                mass:            1.0,
+                shape:           Shape::Circle(Circle::new(radius)),
+                constitution:    1.0,
+                friction:        DEFAULT_FRICTION,
+                static_friction: DEFAULT_STATIC_FRICTION,
+                body_type:       BodyType::Dynamic,
+                sensor:          false,
             });
     }
 
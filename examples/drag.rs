@@ -0,0 +1,120 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use phusis::{
+    bevy::{BodyType, Collider, Gravity, PhusisBevyPlugin, PhysicsWorldResource},
+    body::{DEFAULT_FRICTION, DEFAULT_STATIC_FRICTION},
+    joint::Joint,
+    shape::{Circle, Shape},
+    world::JointHandle,
+};
+use rand::prelude::*;
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+}
+
+fn add_bodies(mut commands: Commands) {
+    let mut rng = rand::thread_rng();
+
+    commands
+        .spawn(Transform::from_xyz(0.0, -300.0, 1.0))
+        .insert(Collider {
+            mass:            1.0,
+            shape:           Shape::Rect(phusis::Vec2::new(1200.0, 40.0)),
+            constitution:    1.0,
+            friction:        DEFAULT_FRICTION,
+            static_friction: DEFAULT_STATIC_FRICTION,
+            body_type:       BodyType::Fixed,
+            sensor:          false,
+        });
+
+    for _ in 0..40 {
+        let x = rng.gen_range(-400..400) as f32;
+        let y = rng.gen_range(-200..300) as f32;
+        let shape = if rng.gen_bool(0.5) {
+            Shape::Circle(Circle::new(20.0))
+        } else {
+            Shape::Rect(phusis::Vec2::new(40.0, 40.0))
+        };
+
+        commands
+            .spawn(Transform::from_xyz(x, y, 1.0))
+            .insert(Collider {
+                mass: 1.0,
+                shape,
+                constitution: 1.0,
+                friction: DEFAULT_FRICTION,
+                static_friction: DEFAULT_STATIC_FRICTION,
+                body_type: BodyType::Dynamic,
+                sensor: false,
+            });
+    }
+}
+
+/**
+ * Grabs the body under the cursor with a target joint while the left mouse
+ * button is held, the joint follows the cursor until the button is released
+ */
+fn drag_bodies(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    buttons: Res<Input<MouseButton>>,
+    mut physics_world: ResMut<PhysicsWorldResource>,
+    mut dragging: Local<Option<JointHandle>>,
+) {
+    let physics_world = &mut physics_world.physics_world;
+    if buttons.just_released(MouseButton::Left) {
+        if let Some(joint) = dragging.take() {
+            physics_world.remove_joint(joint);
+        }
+    }
+
+    let (camera, camera_transform) = cameras.single();
+    let Some(cursor) = windows
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+        .map(|point| phusis::Vec2::new(point.x, point.y))
+    else {
+        return;
+    };
+
+    if let Some(joint) = *dragging {
+        physics_world.update_joint(joint, |joint| joint.set_target(cursor));
+    } else if buttons.just_pressed(MouseButton::Left) {
+        let grabbed = physics_world
+            .bodies_at_point(cursor, |_, body| body.body_type.is_dynamic())
+            .into_iter()
+            .next();
+        if let Some(handle) = grabbed {
+            let body = physics_world.get_body(handle).unwrap();
+            // Springs at 5 hz that are slightly underdamped, strong enough
+            // to lift the body against ten times its weight
+            let omega = 2.0 * PI * 5.0;
+            let anchor = (cursor - body.position).rotate(-body.angle);
+            let joint = Joint::target(
+                handle,
+                anchor,
+                cursor,
+                body.mass() * omega * omega,
+                2.0 * body.mass() * 0.7 * omega,
+                body.mass() * 5000.0,
+            );
+            *dragging = Some(physics_world.add_joint(joint));
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .insert_resource(Msaa::default())
+        .insert_resource(Gravity(Vec2::new(0.0, -500.0)))
+        .add_plugins(DefaultPlugins)
+        .add_plugin(PhusisBevyPlugin)
+        .add_startup_system(setup)
+        .add_startup_system(add_bodies)
+        .add_system(drag_bodies)
+        .run();
+}
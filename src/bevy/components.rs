@@ -1,35 +1,272 @@
+use std::{collections::HashMap, marker::PhantomData};
+
 use bevy::prelude::*;
 use generational_arena::Index;
 
-use crate::{quad_tree::QuadTree, shape::Shape, world::PhysicsWorld};
+use crate::{
+    body::{BodyType, ColliderPart},
+    joint::{Joint, Motor},
+    quad_tree::QuadTree,
+    shape::Shape,
+    world::PhysicsWorld,
+};
 
 #[derive(Resource)]
 pub struct PhysicsWorldResource {
-    pub physics_world: PhysicsWorld<QuadTree<Index>>,
+    pub physics_world:   PhysicsWorld<QuadTree<Index>>,
+    /**
+     * Entities of the bodies, kept until the events of removed bodies are sent
+     */
+    pub(crate) entities: HashMap<Index, Entity>,
+}
+
+impl PhysicsWorldResource {
+    #[must_use]
+    pub fn new(physics_world: PhysicsWorld<QuadTree<Index>>) -> Self {
+        Self {
+            physics_world,
+            entities: HashMap::new(),
+        }
+    }
 }
+
+/**
+ * Gravity of the physics world, can be changed at runtime
+ */
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct Gravity(pub Vec2);
+
 #[derive(Component)]
 pub struct Collider {
-    pub shape:        Shape,
-    pub mass:         f32,
-    pub constitution: f32,
-    pub fixed:        bool,
-    pub sensor:       bool,
+    pub shape:           Shape,
+    pub mass:            f32,
+    pub constitution:    f32,
+    /**
+     * Coulomb friction coefficients of the body,
+     * see `Body::friction` and `Body::static_friction`
+     */
+    pub friction:        f32,
+    pub static_friction: f32,
+    pub body_type:       BodyType,
+    pub sensor:          bool,
 }
 
+/**
+ * Extra colliders of the body of a `Collider` entity,
+ * placed relative to it, see `Body::colliders`
+ */
+#[derive(Component, Debug, Clone, Default)]
+pub struct CompoundCollider(pub Vec<ColliderPart>);
+
+/**
+ * Density of a `Collider` in mass per unit of area,
+ * the mass of the body is computed from its colliders instead of `Collider::mass`
+ */
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Density(pub f32);
+
+/**
+ * Collision layers of a `Collider`, see `Body::memberships`,
+ * `Body::filter` and `Body::group`
+ */
+#[derive(Component, Debug, Clone, Copy)]
+pub struct CollisionLayers {
+    pub memberships: u32,
+    pub filter:      u32,
+    pub group:       Option<i32>,
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self {
+            memberships: u32::MAX,
+            filter:      u32::MAX,
+            group:       None,
+        }
+    }
+}
+
+/**
+ * Contact event between the entities of two bodies,
+ * sent for every step of the physics world
+ */
+pub type CollisionEvent = crate::collision::CollisionEvent<Entity>;
+
 #[derive(Component, Default)]
 pub struct Collisions {
     pub entities: Vec<Entity>,
 }
 
-#[derive(Component)]
-pub struct Fixed;
+/**
+ * Whether the body of an entity is sleeping, kept up to date
+ * with the physics world. Setting it puts the body to sleep or wakes it up
+ */
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sleeping(pub bool);
 
 #[derive(Component)]
 pub struct ComponentBodyHandle {
     pub handle: Index,
+    /**
+     * Transform last written from the physics world,
+     * any other change to the transform moves the body
+     */
+    pub synced: Transform,
+}
+
+/**
+ * Drives the body of an entity as a kinematic body,
+ * sets its `BodyType` to match
+ */
+#[derive(Component, Debug, Clone, Copy)]
+pub enum KinematicController {
+    /**
+     * Moves the body with a velocity in units and radians per second
+     */
+    Velocity { linear: Vec2, angular: f32 },
+    /**
+     * Moves the body to a position and angle during the next step
+     */
+    Position { translation: Vec2, angle: f32 },
+}
+
+/**
+ * A component connecting the body of its entity to the body of another entity,
+ * the body of `other` becomes the first body of the joint
+ */
+pub trait JointComponent: Component {
+    fn other(&self) -> Entity;
+
+    fn joint(&self, other: Index, this: Index) -> Joint;
 }
 
 #[derive(Component)]
-pub struct KinematicController {
-    pub translation: Vec2,
+pub struct ComponentJointHandle<J: Send + Sync + 'static> {
+    pub handle: Index,
+    marker:     PhantomData<J>,
+}
+
+impl<J: Send + Sync + 'static> ComponentJointHandle<J> {
+    pub fn new(handle: Index) -> Self {
+        Self {
+            handle,
+            marker: PhantomData,
+        }
+    }
+}
+
+fn to_vec2(vec: Vec2) -> crate::Vec2<f32> {
+    crate::Vec2::new(vec.x, vec.y)
+}
+
+/**
+ * Hinges the body of the entity to the body of `other`,
+ * see `JointKind::Revolute`
+ */
+#[derive(Component, Debug, Clone, Copy)]
+pub struct RevoluteJoint {
+    pub other:        Entity,
+    /**
+     * Anchor relative to the body of the entity
+     */
+    pub anchor:       Vec2,
+    /**
+     * Anchor relative to the body of `other`
+     */
+    pub other_anchor: Vec2,
+    pub limits:       Option<(f32, f32)>,
+    pub motor:        Option<Motor>,
+}
+
+impl JointComponent for RevoluteJoint {
+    fn other(&self) -> Entity {
+        self.other
+    }
+
+    fn joint(&self, other: Index, this: Index) -> Joint {
+        let mut joint = Joint::revolute(
+            other,
+            this,
+            to_vec2(self.other_anchor),
+            to_vec2(self.anchor),
+        );
+        if let Some((lower, upper)) = self.limits {
+            joint = joint.with_limits(lower, upper);
+        }
+        if let Some(motor) = self.motor {
+            joint = joint.with_motor(motor.speed, motor.max_force);
+        }
+        joint
+    }
+}
+
+/**
+ * Lets the body of the entity slide along an axis fixed to the body of `other`,
+ * see `JointKind::Prismatic`
+ */
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PrismaticJoint {
+    pub other:        Entity,
+    pub anchor:       Vec2,
+    pub other_anchor: Vec2,
+    /**
+     * Axis relative to the body of `other`
+     */
+    pub axis:         Vec2,
+    pub limits:       Option<(f32, f32)>,
+    pub motor:        Option<Motor>,
+}
+
+impl JointComponent for PrismaticJoint {
+    fn other(&self) -> Entity {
+        self.other
+    }
+
+    fn joint(&self, other: Index, this: Index) -> Joint {
+        let mut joint = Joint::prismatic(
+            other,
+            this,
+            to_vec2(self.other_anchor),
+            to_vec2(self.anchor),
+            to_vec2(self.axis),
+        );
+        if let Some((lower, upper)) = self.limits {
+            joint = joint.with_limits(lower, upper);
+        }
+        if let Some(motor) = self.motor {
+            joint = joint.with_motor(motor.speed, motor.max_force);
+        }
+        joint
+    }
+}
+
+/**
+ * Glues the body of the entity to the body of `other`,
+ * see `JointKind::Weld`
+ */
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WeldJoint {
+    pub other:           Entity,
+    pub anchor:          Vec2,
+    pub other_anchor:    Vec2,
+    /**
+     * Angle of the body of the entity relative to the body of `other`
+     */
+    pub reference_angle: f32,
+}
+
+impl JointComponent for WeldJoint {
+    fn other(&self) -> Entity {
+        self.other
+    }
+
+    fn joint(&self, other: Index, this: Index) -> Joint {
+        Joint::weld(
+            other,
+            this,
+            to_vec2(self.other_anchor),
+            to_vec2(self.anchor),
+        )
+        .with_reference_angle(self.reference_angle)
+    }
 }
@@ -1,57 +1,165 @@
+// Bevy hands system parameters over by value
+#![allow(clippy::needless_pass_by_value)]
+
 use bevy::prelude::*;
 
-use super::components::Collider;
+use super::components::{Collider, CompoundCollider};
 use crate::shape::Shape;
 
+fn debug_color(fixed: bool, sensor: bool) -> Color {
+    match (fixed, sensor) {
+        (true, true) => Color::GREEN,
+        (true, false) => Color::BLUE,
+        (false, true) => Color::YELLOW,
+        (false, false) => Color::RED,
+    }
+}
+
+fn shape_path(shape: &Shape, scale: f32) -> bevy_prototype_lyon::prelude::Path {
+    use bevy_prototype_lyon::prelude::*;
+
+    match shape {
+        Shape::Circle(circle) => {
+            let shape = shapes::RegularPolygon {
+                sides: 24,
+                feature: shapes::RegularPolygonFeature::Radius(circle.radius / scale),
+                ..default()
+            };
+            GeometryBuilder::build_as(&shape)
+        },
+        Shape::Rect(rect) => {
+            let shape = shapes::Rectangle {
+                extents: Vec2::new(rect.x / scale, rect.y / scale),
+                ..default()
+            };
+            GeometryBuilder::build_as(&shape)
+        },
+        Shape::Polygon(polygon) => {
+            let shape = shapes::Polygon {
+                points: polygon
+                    .vertices
+                    .iter()
+                    .map(|vertex| Vec2::new(vertex.x / scale, vertex.y / scale))
+                    .collect(),
+                closed: true,
+            };
+            GeometryBuilder::build_as(&shape)
+        },
+        Shape::Capsule {
+            half_length,
+            radius,
+        } => {
+            let (half_length, radius) = (half_length / scale, radius / scale);
+            let mut path_builder = PathBuilder::new();
+            path_builder.move_to(Vec2::new(radius, -half_length));
+            path_builder.line_to(Vec2::new(radius, half_length));
+            path_builder.arc(
+                Vec2::new(0.0, half_length),
+                Vec2::new(radius, radius),
+                std::f32::consts::PI,
+                0.0,
+            );
+            path_builder.line_to(Vec2::new(-radius, -half_length));
+            path_builder.arc(
+                Vec2::new(0.0, -half_length),
+                Vec2::new(radius, radius),
+                std::f32::consts::PI,
+                0.0,
+            );
+            path_builder.close();
+            path_builder.build()
+        },
+        Shape::Segment { a, b } => {
+            let shape = shapes::Line(
+                Vec2::new(a.x / scale, a.y / scale),
+                Vec2::new(b.x / scale, b.y / scale),
+            );
+            GeometryBuilder::build_as(&shape)
+        },
+    }
+}
+
+/**
+ * Marks the outlines of extra colliders, redrawn when the colliders change
+ */
+#[derive(Component)]
+struct ColliderPartOutline;
+
+/**
+ * Colliders whose outlines have to be redrawn
+ */
+type ChangedColliders<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Collider,
+        &'static Transform,
+        Entity,
+        Option<&'static CompoundCollider>,
+        Option<&'static Children>,
+    ),
+    Or<(Changed<Collider>, Changed<CompoundCollider>)>,
+>;
+
 fn debug_physics(
     mut commands: Commands,
-    query: Query<(&Collider, &Transform, Entity), Changed<Collider>>,
+    query: ChangedColliders,
+    outlines: Query<(), With<ColliderPartOutline>>,
 ) {
     use bevy_prototype_lyon::prelude::*;
 
-    for (body, transform, entity) in query.iter() {
-        let color = match (body.fixed, body.sensor) {
-            (true, true) => Color::GREEN,
-            (true, false) => Color::BLUE,
-            (false, true) => Color::YELLOW,
-            (false, false) => Color::RED,
-        };
+    for (body, transform, entity, compound, children) in query.iter() {
+        // Outlines of the previous extra colliders are replaced
+        if let Some(children) = children {
+            for child in children {
+                if outlines.contains(*child) {
+                    commands.entity(*child).despawn_recursive();
+                }
+            }
+        }
+
+        let fixed = !body.body_type.is_dynamic();
+        let color = debug_color(fixed, body.sensor);
 
         let scale = 1.0; // 6.0; // transform.scale.x;
 
-        match &body.shape {
-            Shape::Circle(circle) => {
-                let shape = shapes::RegularPolygon {
-                    sides: 24,
-                    feature: shapes::RegularPolygonFeature::Radius(circle.radius / scale),
-                    ..default()
-                };
-                commands.entity(entity).insert((
-                    ShapeBundle {
-                        path: GeometryBuilder::build_as(&shape),
-                        transform: *transform,
-                        ..default()
-                    },
-                    Fill::color(Color::rgba(0.0, 0.0, 0.0, 0.0)),
-                    Stroke::new(color, 1.0 / scale),
-                ));
-            },
-            Shape::Rect(rect) => {
-                let shape = shapes::Rectangle {
-                    extents: Vec2::new(rect.x / scale, rect.y / scale),
-                    ..default()
-                };
-                commands.entity(entity).insert((
-                    ShapeBundle {
-                        path: GeometryBuilder::build_as(&shape),
-                        transform: *transform,
-                        ..default()
-                    },
-                    Fill::color(Color::rgba(0.0, 0.0, 0.0, 0.0)),
-                    Stroke::new(color, 1.0 / scale),
-                ));
+        let path = shape_path(&body.shape, scale);
+        commands.entity(entity).insert((
+            ShapeBundle {
+                path,
+                transform: *transform,
+                ..default()
             },
-        };
+            Fill::color(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+            Stroke::new(color, 1.0 / scale),
+        ));
+
+        // Extra colliders are drawn as children placed at their offsets
+        if let Some(compound) = compound {
+            commands.entity(entity).with_children(|parent| {
+                for collider in &compound.0 {
+                    let transform = Transform::from_xyz(
+                        collider.offset.x / scale,
+                        collider.offset.y / scale,
+                        0.0,
+                    )
+                    .with_rotation(Quat::from_rotation_z(collider.angle));
+                    parent.spawn((
+                        ShapeBundle {
+                            path: shape_path(&collider.shape, scale),
+                            transform,
+                            ..default()
+                        },
+                        Fill::color(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+                        Stroke::new(
+                            debug_color(fixed, body.sensor || collider.sensor),
+                            1.0 / scale,
+                        ),
+                        ColliderPartOutline,
+                    ));
+                }
+            });
+        }
     }
 }
 
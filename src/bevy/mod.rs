@@ -6,28 +6,80 @@ mod components;
 mod debug;
 mod systems;
 
-pub use components::{Collider, Collisions};
+pub use components::{
+    Collider,
+    CollisionEvent,
+    CollisionLayers,
+    Collisions,
+    CompoundCollider,
+    Density,
+    Gravity,
+    JointComponent,
+    KinematicController,
+    PhysicsWorldResource,
+    PrismaticJoint,
+    RevoluteJoint,
+    Sleeping,
+    WeldJoint,
+};
 
-use self::components::PhysicsWorldResource;
+pub use crate::{
+    body::{BodyType, ColliderPart},
+    joint::Motor,
+    world::IntegrationParameters,
+};
 use crate::{shape::AABB, world::PhysicsWorld, QuadTree};
 
+/**
+ * Registers the systems that keep the joints of a `JointComponent`
+ * in sync with the physics world
+ */
+pub fn add_joint_systems<J: JointComponent>(app: &mut App) {
+    app.add_system(
+        systems::on_joint_change::<J>
+            .before(systems::update_physics)
+            .after(systems::on_body_change),
+    )
+    .add_system(systems::on_joint_removed::<J>.before(systems::update_physics));
+}
+
 pub struct PhusisBevyPlugin;
 
 impl Plugin for PhusisBevyPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(PhysicsWorldResource {
-            physics_world: PhysicsWorld::new(QuadTree::new(
-                0,
-                AABB::new(-5000, -5000, 10000, 10000),
-            )),
-        })
+        app.insert_resource(PhysicsWorldResource::new(PhysicsWorld::new(
+            QuadTree::new(0, AABB::new(-5000, -5000, 10000, 10000)),
+        )))
+        .init_resource::<Gravity>()
+        .init_resource::<IntegrationParameters>()
+        .add_event::<CollisionEvent>()
         .add_system(systems::on_body_change)
+        .add_system(
+            systems::on_collision_layers_change
+                .before(systems::update_physics)
+                .after(systems::on_body_change),
+        )
+        .add_system(
+            systems::on_sleeping_change
+                .before(systems::update_physics)
+                .after(systems::on_body_change),
+        )
+        .add_system(
+            systems::on_kinematic_controller_change
+                .before(systems::update_physics)
+                .after(systems::on_body_change),
+        )
+        .add_system(systems::on_gravity_change.before(systems::update_physics))
+        .add_system(systems::on_integration_parameters_change.before(systems::update_physics))
         .add_system(systems::update_physics)
         .add_system(
             systems::on_body_transform_change
                 .before(systems::update_physics)
                 .after(systems::on_body_change),
         );
+        add_joint_systems::<RevoluteJoint>(app);
+        add_joint_systems::<PrismaticJoint>(app);
+        add_joint_systems::<WeldJoint>(app);
 
         #[cfg(feature = "bevy_debug")]
         app.add_plugin(ShapePlugin);
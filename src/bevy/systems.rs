@@ -1,28 +1,220 @@
+// Bevy hands system parameters over by value
+#![allow(clippy::needless_pass_by_value)]
+
 use bevy::prelude::*;
 
 use super::components::PhysicsWorldResource;
 use crate::{
-    bevy::components::{Collider, Collisions, ComponentBodyHandle},
-    body::Body,
+    bevy::components::{
+        Collider,
+        CollisionEvent,
+        CollisionLayers,
+        Collisions,
+        ComponentBodyHandle,
+        ComponentJointHandle,
+        CompoundCollider,
+        Density,
+        Gravity,
+        JointComponent,
+        KinematicController,
+        Sleeping,
+    },
+    body::{Body, BodyType},
+    world::IntegrationParameters,
 };
 
+/**
+ * Rotation of a transform around the z axis in radians
+ */
+fn transform_angle(transform: &Transform) -> f32 {
+    transform.rotation.to_euler(EulerRot::ZYX).0
+}
+
+/**
+ * Newly added colliders with the optional components that shape their body
+ */
+type AddedColliders<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Collider,
+        &'static Transform,
+        Entity,
+        Option<&'static CollisionLayers>,
+        Option<&'static CompoundCollider>,
+        Option<&'static Density>,
+    ),
+    Added<Collider>,
+>;
+
 pub fn on_body_change(
     mut commands: Commands,
     mut physics_world: ResMut<PhysicsWorldResource>,
-    query: Query<(&Collider, &Transform, Entity), Added<Collider>>,
+    query: AddedColliders,
 ) {
-    for (collider, transform, entity) in query.iter() {
-        let handle = physics_world.physics_world.add_body(Body {
+    for (collider, transform, entity, layers, compound, density) in query.iter() {
+        let layers = layers.copied().unwrap_or_default();
+        let mut body = Body {
             shape: collider.shape.clone(),
             position: crate::Vec2::new(transform.translation.x, transform.translation.y),
-            fixed: collider.fixed,
+            angle: transform_angle(transform),
+            friction: collider.friction,
+            static_friction: collider.static_friction,
+            body_type: collider.body_type,
             sensor: collider.sensor,
+            memberships: layers.memberships,
+            filter: layers.filter,
+            group: layers.group,
+            colliders: compound
+                .map(|compound| compound.0.clone())
+                .unwrap_or_default(),
             entity,
             ..default()
+        };
+        match density {
+            Some(density) => body.set_density(density.0),
+            None => body.set_mass(collider.mass),
+        }
+        let handle = physics_world.physics_world.add_body(body);
+        physics_world.entities.insert(handle, entity);
+        commands.entity(entity).insert(ComponentBodyHandle {
+            handle,
+            synced: *transform,
         });
+    }
+}
+
+pub fn on_collision_layers_change(
+    mut physics_world: ResMut<PhysicsWorldResource>,
+    query: Query<(&ComponentBodyHandle, &CollisionLayers), Changed<CollisionLayers>>,
+) {
+    for (body_handle, layers) in query.iter() {
+        if let Some(body) = physics_world
+            .physics_world
+            .bodies
+            .get_mut(body_handle.handle)
+        {
+            body.memberships = layers.memberships;
+            body.filter = layers.filter;
+            body.group = layers.group;
+        }
+    }
+}
+
+pub fn on_sleeping_change(
+    mut physics_world: ResMut<PhysicsWorldResource>,
+    query: Query<(&ComponentBodyHandle, &Sleeping), Changed<Sleeping>>,
+) {
+    for (body_handle, sleeping) in query.iter() {
+        let physics_world = &mut physics_world.physics_world;
+        if sleeping.0 == physics_world.is_sleeping(body_handle.handle) {
+            continue;
+        }
+        if sleeping.0 {
+            physics_world.sleep_body(body_handle.handle);
+        } else {
+            physics_world.wake_body(body_handle.handle);
+        }
+    }
+}
+
+/**
+ * Controllers that changed or whose body was just added
+ */
+type KinematicControllerChanged = Or<(Changed<KinematicController>, Added<ComponentBodyHandle>)>;
+
+pub fn on_kinematic_controller_change(
+    mut physics_world: ResMut<PhysicsWorldResource>,
+    query: Query<(&ComponentBodyHandle, &KinematicController), KinematicControllerChanged>,
+) {
+    for (body_handle, controller) in query.iter() {
+        physics_world
+            .physics_world
+            .update(&body_handle.handle, |body| match *controller {
+                KinematicController::Velocity { linear, angular } => {
+                    body.body_type = BodyType::KinematicVelocity;
+                    body.velocity = crate::Vec2::new(linear.x, linear.y);
+                    body.angular_velocity = angular;
+                },
+                KinematicController::Position { translation, angle } => {
+                    body.body_type = BodyType::KinematicPosition;
+                    body.kinematic_target =
+                        Some((crate::Vec2::new(translation.x, translation.y), angle));
+                },
+            });
+    }
+}
+
+/**
+ * Joint components that changed or haven't been added to the world yet
+ */
+type ChangedJoints<'w, 's, J> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static ComponentBodyHandle,
+        &'static J,
+        Option<&'static ComponentJointHandle<J>>,
+    ),
+    Or<(Changed<J>, Without<ComponentJointHandle<J>>)>,
+>;
+
+/**
+ * Adds the joint of a component once the bodies of both entities exist,
+ * changing the component replaces the joint
+ */
+pub fn on_joint_change<J: JointComponent>(
+    mut commands: Commands,
+    mut physics_world: ResMut<PhysicsWorldResource>,
+    query: ChangedJoints<J>,
+    bodies: Query<&ComponentBodyHandle>,
+) {
+    for (entity, body_handle, joint, joint_handle) in query.iter() {
+        let Ok(other) = bodies.get(joint.other()) else {
+            continue;
+        };
+        let physics_world = &mut physics_world.physics_world;
+        if let Some(joint_handle) = joint_handle {
+            physics_world.remove_joint(joint_handle.handle);
+        }
+        let handle = physics_world.add_joint(joint.joint(other.handle, body_handle.handle));
         commands
             .entity(entity)
-            .insert(ComponentBodyHandle { handle });
+            .insert(ComponentJointHandle::<J>::new(handle));
+    }
+}
+
+pub fn on_joint_removed<J: JointComponent>(
+    mut commands: Commands,
+    mut physics_world: ResMut<PhysicsWorldResource>,
+    mut removed: RemovedComponents<J>,
+    query: Query<&ComponentJointHandle<J>>,
+) {
+    for entity in &mut removed {
+        if let Ok(joint_handle) = query.get(entity) {
+            physics_world
+                .physics_world
+                .remove_joint(joint_handle.handle);
+            commands.entity(entity).remove::<ComponentJointHandle<J>>();
+        }
+    }
+}
+
+pub fn on_gravity_change(gravity: Res<Gravity>, mut physics_world: ResMut<PhysicsWorldResource>) {
+    if gravity.is_changed() {
+        physics_world.physics_world.gravity = crate::Vec2::new(gravity.0.x, gravity.0.y);
+    }
+}
+
+pub fn on_integration_parameters_change(
+    parameters: Res<IntegrationParameters>,
+    mut physics_world: ResMut<PhysicsWorldResource>,
+) {
+    if parameters.is_changed() {
+        physics_world
+            .physics_world
+            .set_integration_parameters(*parameters);
     }
 }
 
@@ -31,10 +223,17 @@ pub fn on_body_transform_change(
     query: Query<(&ComponentBodyHandle, &Transform), Changed<Transform>>,
 ) {
     for (body_handle, transform) in query.iter() {
+        if *transform == body_handle.synced {
+            continue;
+        }
         physics_world
             .physics_world
             .update(&body_handle.handle, |body| {
                 body.position = crate::Vec2::new(transform.translation.x, transform.translation.y);
+                body.angle = transform_angle(transform);
+                // Moved by hand, don't interpolate from the old position
+                body.previous_position = body.position;
+                body.previous_angle = body.angle;
             });
     }
 }
@@ -42,42 +241,198 @@ pub fn on_body_transform_change(
 pub fn update_physics(
     time: Res<Time>,
     mut physics_world: ResMut<PhysicsWorldResource>,
-    mut query: Query<(&ComponentBodyHandle, &mut Transform)>,
+    mut query: Query<(
+        &mut ComponentBodyHandle,
+        &mut Transform,
+        Option<&mut Sleeping>,
+    )>,
     mut collisions_q: Query<&mut Collisions>,
+    mut events: EventWriter<CollisionEvent>,
 ) {
-    let collisions = physics_world
+    let update = physics_world
         .physics_world
-        .update_with_quad(time.delta_seconds());
+        .update_fixed(time.delta_seconds());
 
-    for mut collision in collisions_q.iter_mut() {
-        collision.entities.clear();
-    }
+    // Frames without a step keep the contacts of the last one
+    if update.steps > 0 {
+        for mut collision in &mut collisions_q {
+            collision.entities.clear();
+        }
 
-    for collision in collisions {
-        if let Ok(mut collision_entity) = collisions_q.get_mut(
-            physics_world
-                .physics_world
-                .get_body(collision.pair.a)
-                .unwrap()
-                .entity,
-        ) {
-            collision_entity.entities.push(
+        for collision in update.collisions {
+            if let Ok(mut collision_entity) = collisions_q.get_mut(
                 physics_world
                     .physics_world
-                    .get_body(collision.pair.b)
+                    .get_body(collision.pair.a)
                     .unwrap()
                     .entity,
-            );
+            ) {
+                // Compound bodies can touch through several colliders at once
+                let entity = physics_world
+                    .physics_world
+                    .get_body(collision.pair.b)
+                    .unwrap()
+                    .entity;
+                if !collision_entity.entities.contains(&entity) {
+                    collision_entity.entities.push(entity);
+                }
+            }
         }
     }
 
-    for (body_handle, mut transform) in query.iter_mut() {
+    // Removed bodies end their contacts in the next step,
+    // their entities are kept until then
+    events.send_batch(
+        update
+            .events
+            .into_iter()
+            .filter_map(|event| {
+                event.try_map(|handle| {
+                    physics_world
+                        .physics_world
+                        .get_body(handle)
+                        .map(|body| body.entity)
+                        .or_else(|| physics_world.entities.get(&handle).copied())
+                })
+            }),
+    );
+    if update.steps > 0 {
+        let PhysicsWorldResource {
+            physics_world,
+            entities,
+        } = &mut *physics_world;
+        entities.retain(|handle, _| physics_world.get_body(*handle).is_some());
+    }
+
+    for (mut body_handle, mut transform, sleeping) in &mut query {
         if let Some(body) = physics_world.physics_world.get_body(body_handle.handle) {
-            if transform.translation.x != body.position.x
-                && transform.translation.y != body.position.y
-            {
-                transform.translation = Vec3::new(body.position.x, body.position.y, 1.0);
+            if let Some(mut sleeping) = sleeping {
+                if sleeping.0 != body.sleeping {
+                    sleeping.0 = body.sleeping;
+                }
+            }
+            let position = body.interpolated_position(update.alpha);
+            let translation = Vec3::new(position.x, position.y, transform.translation.z);
+            if transform.translation != translation {
+                transform.translation = translation;
             }
+            let rotation = Quat::from_rotation_z(body.interpolated_angle(update.alpha));
+            if transform.rotation != rotation {
+                transform.rotation = rotation;
+            }
+            body_handle.synced = *transform;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::{
+        body::{DEFAULT_FRICTION, DEFAULT_STATIC_FRICTION},
+        shape::{Circle, Shape, AABB},
+        world::PhysicsWorld,
+        QuadTree,
+    };
+
+    fn collider(radius: f32, body_type: BodyType) -> Collider {
+        Collider {
+            shape: Shape::Circle(Circle::new(radius)),
+            mass: 1.0,
+            constitution: 0.5,
+            friction: DEFAULT_FRICTION,
+            static_friction: DEFAULT_STATIC_FRICTION,
+            body_type,
+            sensor: true,
+        }
+    }
+
+    fn advance(app: &mut App, now: &mut Instant, seconds: f32) {
+        *now += Duration::from_secs_f32(seconds);
+        app.world.resource_mut::<Time>().update_with_instant(*now);
+        app.update();
+    }
+
+    /**
+     * App with a small sensor overlapping a large fixed body
+     */
+    fn app(now: Instant) -> (App, [Entity; 2]) {
+        let mut time = Time::new(now);
+        time.update_with_instant(now);
+
+        let mut app = App::new();
+        app.insert_resource(time)
+            .insert_resource(PhysicsWorldResource::new(PhysicsWorld::new(
+                QuadTree::new(0, AABB::new(-100, -100, 200, 200)),
+            )))
+            .add_event::<CollisionEvent>()
+            .add_system(on_body_change)
+            .add_system(update_physics.after(on_body_change));
+        let bodies = [
+            collider(10.0, BodyType::Fixed),
+            collider(1.0, BodyType::Dynamic),
+        ]
+        .map(|collider| {
+            app.world
+                .spawn((collider, Transform::default(), Collisions::default()))
+                .id()
+        });
+        // Adds the bodies to the physics world
+        app.update();
+        (app, bodies)
+    }
+
+    #[test]
+    fn it_keeps_collisions_between_steps() {
+        let mut now = Instant::now();
+        let (mut app, bodies) = app(now);
+        let contacts = |app: &mut App| -> usize {
+            bodies
+                .iter()
+                .map(|entity| app.world.get::<Collisions>(*entity).unwrap().entities.len())
+                .sum()
+        };
+
+        advance(&mut app, &mut now, 0.02);
+        assert_eq!(contacts(&mut app), 1);
+
+        // Less than a timestep takes no step
+        advance(&mut app, &mut now, 0.001);
+        assert_eq!(contacts(&mut app), 1);
+    }
+
+    #[test]
+    fn it_ends_collisions_of_removed_bodies() {
+        let mut now = Instant::now();
+        let (mut app, bodies) = app(now);
+        advance(&mut app, &mut now, 0.02);
+
+        let handle = app
+            .world
+            .get::<ComponentBodyHandle>(bodies[1])
+            .unwrap()
+            .handle;
+        app.world
+            .resource_mut::<PhysicsWorldResource>()
+            .physics_world
+            .remove_body(&handle);
+        let mut reader = app
+            .world
+            .resource::<Events<CollisionEvent>>()
+            .get_reader_current();
+        // Less than a timestep first, the entity has to be kept until the next step
+        advance(&mut app, &mut now, 0.001);
+        advance(&mut app, &mut now, 0.02);
+
+        let events = app.world.resource::<Events<CollisionEvent>>();
+        let ended: Vec<_> = reader
+            .iter(events)
+            .filter(|event| matches!(event, CollisionEvent::Ended { .. }))
+            .map(CollisionEvent::pair)
+            .collect();
+        assert_eq!(ended.len(), 1);
+        assert!(ended[0].a == bodies[1] || ended[0].b == bodies[1]);
+    }
+}
@@ -2,24 +2,226 @@
 use bevy::prelude::Entity;
 
 use crate::{
-    shape::{Circle, Shape, AABB},
+    shape::{Circle, MassProperties, Shape, AABB},
     Vec2,
 };
 
+/**
+ * How a body is moved by the world
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyType {
+    /**
+     * Moved by forces, gravity and contacts
+     */
+    #[default]
+    Dynamic,
+    /**
+     * Never moves
+     */
+    Fixed,
+    /**
+     * Moves by the velocity it is given,
+     * pushes dynamic bodies without being pushed back
+     */
+    KinematicVelocity,
+    /**
+     * Moves to `Body::kinematic_target` every step,
+     * its velocity follows from the movement
+     */
+    KinematicPosition,
+}
+
+impl BodyType {
+    #[must_use]
+    pub fn is_dynamic(self) -> bool {
+        self == BodyType::Dynamic
+    }
+
+    #[must_use]
+    pub fn is_fixed(self) -> bool {
+        self == BodyType::Fixed
+    }
+
+    #[must_use]
+    pub fn is_kinematic(self) -> bool {
+        matches!(
+            self,
+            BodyType::KinematicVelocity | BodyType::KinematicPosition
+        )
+    }
+}
+
+/**
+ * Coulomb friction coefficients bodies and colliders start with
+ */
+pub const DEFAULT_FRICTION: f32 = 0.5;
+pub const DEFAULT_STATIC_FRICTION: f32 = 0.6;
+
+/**
+ * Density in mass per unit of area that `Body::default` takes its mass from
+ */
+pub const DEFAULT_DENSITY: f32 = 1.0;
+
+/**
+ * Inverse of a mass or inertia, 0 for the zero and infinite values
+ * that can't be moved
+ */
+fn inverse(value: f32) -> f32 {
+    if value > 0f32 && value.is_finite() {
+        1f32 / value
+    } else {
+        0f32
+    }
+}
+
+/**
+ * An additional shape of a body, placed relative to the body
+ * with its own sensor flag and material
+ */
+#[derive(Debug, Clone)]
+pub struct ColliderPart {
+    pub shape:           Shape,
+    /**
+     * Position and angle relative to the body, in its local space
+     */
+    pub offset:          Vec2<f32>,
+    pub angle:           f32,
+    /**
+     * Sensor colliders report overlaps without being resolved,
+     * every collider of a sensor body is a sensor
+     */
+    pub sensor:          bool,
+    pub restitution:     f32,
+    pub friction:        f32,
+    pub static_friction: f32,
+}
+
+impl ColliderPart {
+    #[must_use]
+    pub fn new(shape: Shape, offset: Vec2<f32>, angle: f32) -> Self {
+        Self {
+            shape,
+            offset,
+            angle,
+            sensor: false,
+            restitution: 0f32,
+            friction: DEFAULT_FRICTION,
+            static_friction: DEFAULT_STATIC_FRICTION,
+        }
+    }
+}
+
+/**
+ * A collider of a body placed in world space
+ */
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PlacedCollider<'a> {
+    /**
+     * 0 for the shape of the body, `i + 1` for `Body::colliders[i]`
+     */
+    pub index:           usize,
+    pub shape:           &'a Shape,
+    pub position:        Vec2<f32>,
+    pub angle:           f32,
+    pub sensor:          bool,
+    pub restitution:     f32,
+    pub friction:        f32,
+    pub static_friction: f32,
+}
+
 #[derive(Debug)]
 pub struct Body {
-    pub position:    Vec2<f32>,
-    pub velocity:    Vec2<f32>,
-    pub force:       Vec2<f32>, // TODO: is this needed
-    pub mass:        f32,
-    pub inv_mass:    f32,
-    pub restitution: f32,
-    pub shape:       Shape,
-    pub friction:    f32,
-    pub fixed:       bool,
-    pub sensor:      bool,
+    pub position:           Vec2<f32>,
+    pub velocity:           Vec2<f32>,
+    pub force:              Vec2<f32>, // TODO: is this needed
+    /**
+     * Multiplier of the world gravity,
+     * 0 for floating bodies and negative for bodies that rise
+     */
+    pub gravity_scale:      f32,
+    pub(crate) mass:        f32,
+    pub(crate) inv_mass:    f32,
+    /**
+     * Orientation in radians, counter-clockwise
+     */
+    pub angle:              f32,
+    /**
+     * Position and angle before the last fixed step,
+     * used to interpolate between fixed steps
+     */
+    pub previous_position:  Vec2<f32>,
+    pub previous_angle:     f32,
+    pub angular_velocity:   f32,
+    pub torque:             f32,
+    pub(crate) inertia:     f32,
+    pub(crate) inv_inertia: f32,
+    pub restitution:        f32,
+    pub shape:              Shape,
+    /**
+     * Shapes attached to the body besides `shape`, collisions report them
+     * as collider `i + 1` while `shape` is collider 0
+     */
+    pub colliders:          Vec<ColliderPart>,
+    /**
+     * Fraction of the linear velocity removed per second,
+     * `None` uses the default of the world
+     */
+    pub linear_damping:     Option<f32>,
+    /**
+     * Fraction of the angular velocity removed per second,
+     * `None` uses the default of the world
+     */
+    pub angular_damping:    Option<f32>,
+    /**
+     * Coulomb friction coefficient of sliding contacts
+     */
+    pub friction:           f32,
+    /**
+     * Coulomb friction coefficient that has to be overcome
+     * before resting contacts start sliding
+     */
+    pub static_friction:    f32,
+    pub body_type:          BodyType,
+    /**
+     * Position and angle a `KinematicPosition` body moves to during a step
+     */
+    pub kinematic_target:   Option<(Vec2<f32>, f32)>,
+    /**
+     * Sensors report overlaps with other bodies
+     * without pushing them or being pushed themselves
+     */
+    pub sensor:             bool,
+    /**
+     * Bit mask of the layers the body belongs to
+     */
+    pub memberships:        u32,
+    /**
+     * Bit mask of the layers the body collides with,
+     * both bodies have to be in the filter of the other one
+     */
+    pub filter:             u32,
+    /**
+     * Bodies sharing a positive group always collide
+     * and bodies sharing a negative group never do, regardless of their layers
+     */
+    pub group:              Option<i32>,
+    /**
+     * Sweeps the body along its path every step and stops it at the first hit,
+     * keeps fast bodies from passing through thin ones
+     */
+    pub ccd:                bool,
+    /**
+     * Sleeping bodies are not simulated until something touches them,
+     * see `PhysicsWorld::wake_body`
+     */
+    pub sleeping:           bool,
+    /**
+     * Seconds the body has been moving slower than the sleep thresholds
+     */
+    pub sleep_time:         f32,
     #[cfg(feature = "bevy")]
-    pub entity:      Entity,
+    pub entity:             Entity,
 }
 
 // #[derive(Debug)]
@@ -32,20 +234,41 @@ pub struct Body {
 
 impl Default for Body {
     fn default() -> Self {
-        Self {
+        let mut body = Self {
             position: Vec2::new(0.0, 0.0),
             velocity: Vec2::new(0.0, 0.0),
             force: Vec2::new(0.0, 0.0),
-            mass: 1.0,
-            inv_mass: 1.0,
+            gravity_scale: 1.0,
+            mass: 0.0,
+            inv_mass: 0.0,
+            angle: 0.0,
+            previous_position: Vec2::new(0.0, 0.0),
+            previous_angle: 0.0,
+            angular_velocity: 0.0,
+            torque: 0.0,
+            inertia: 0.0,
+            inv_inertia: 0.0,
             restitution: 0.0,
             shape: Shape::Circle(Circle::new(1.0)),
-            friction: 0.0,
-            fixed: false,
+            colliders: vec![],
+            linear_damping: Some(0.0),
+            angular_damping: Some(0.0),
+            friction: DEFAULT_FRICTION,
+            static_friction: DEFAULT_STATIC_FRICTION,
+            body_type: BodyType::Dynamic,
+            kinematic_target: None,
             sensor: false,
+            memberships: u32::MAX,
+            filter: u32::MAX,
+            group: None,
+            ccd: false,
+            sleeping: false,
+            sleep_time: 0.0,
             #[cfg(feature = "bevy")]
             entity: Entity::from_bits(0),
-        }
+        };
+        body.set_density(DEFAULT_DENSITY);
+        body
     }
 }
 
@@ -56,28 +279,280 @@ impl Body {
         restitution: f32,
         shape: Shape,
         position: Vec2<f32>,
-        fixed: bool,
+        body_type: BodyType,
         sensor: bool,
         #[cfg(feature = "bevy")] entity: Entity,
     ) -> Self {
-        Body {
-            mass,
+        let mut body = Body {
+            mass: 0f32,
             restitution,
-            inv_mass: 1f32 / mass,
+            inv_mass: 0f32,
             position,
             velocity: Vec2::new(0f32, 0f32),
             force: Vec2::new(0f32, 0f32),
+            gravity_scale: 1f32,
+            angle: 0f32,
+            previous_position: position,
+            previous_angle: 0f32,
+            angular_velocity: 0f32,
+            torque: 0f32,
+            inertia: 0f32,
+            inv_inertia: 0f32,
             shape,
-            friction: 5f32,
-            fixed,
+            colliders: vec![],
+            linear_damping: None,
+            angular_damping: None,
+            friction: DEFAULT_FRICTION,
+            static_friction: DEFAULT_STATIC_FRICTION,
+            body_type,
+            kinematic_target: None,
             sensor,
+            memberships: u32::MAX,
+            filter: u32::MAX,
+            group: None,
+            ccd: false,
+            sleeping: false,
+            sleep_time: 0f32,
             #[cfg(feature = "bevy")]
             entity,
+        };
+        body.set_mass(mass);
+        body
+    }
+
+    #[must_use]
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    /**
+     * Inverse of the mass, 0 for bodies with a zero or infinite mass
+     */
+    #[must_use]
+    pub fn inv_mass(&self) -> f32 {
+        self.inv_mass
+    }
+
+    /**
+     * Center of mass of the colliders relative to the body position,
+     * in its local space. Forces, impulses and rotation act around
+     * the body position rather than this point
+     */
+    #[must_use]
+    pub fn center_of_mass(&self) -> Vec2<f32> {
+        self.mass_properties(1f32).center_of_mass
+    }
+
+    /**
+     * Moment of inertia around the body position
+     */
+    #[must_use]
+    pub fn inertia(&self) -> f32 {
+        self.inertia
+    }
+
+    /**
+     * Inverse of the inertia, 0 for bodies with a zero or infinite inertia
+     */
+    #[must_use]
+    pub fn inv_inertia(&self) -> f32 {
+        self.inv_inertia
+    }
+
+    /**
+     * Sets the mass and recomputes the inertia from the colliders.
+     * Bodies with a zero, negative or infinite mass are not moved by gravity,
+     * forces or impulses and only move with their velocity like kinematic bodies
+     */
+    pub fn set_mass(&mut self, mass: f32) {
+        self.mass = if mass.is_nan() { 0f32 } else { mass.max(0f32) };
+        self.inv_mass = inverse(self.mass);
+        self.compute_inertia();
+    }
+
+    /**
+     * Sets the moment of inertia, zero and infinite inertia
+     * keep the body from being rotated by forces and impulses
+     */
+    pub fn set_inertia(&mut self, inertia: f32) {
+        self.inertia = if inertia.is_nan() {
+            0f32
+        } else {
+            inertia.max(0f32)
+        };
+        self.inv_inertia = inverse(self.inertia);
+    }
+
+    /**
+     * Sets the mass and inertia from the area of the colliders
+     * and a density in mass per unit of area. Bodies whose colliders
+     * have no area, like segments, get a mass of 1 instead
+     */
+    pub fn set_density(&mut self, density: f32) {
+        let properties = self.mass_properties(density);
+        if properties.area > 0f32 {
+            self.set_mass(properties.mass);
+        } else {
+            self.set_mass(1f32);
+        }
+    }
+
+    /**
+     * Recomputes the moment of inertia from the colliders and mass of the body
+     */
+    pub fn compute_inertia(&mut self) {
+        let unit = self.mass_properties(1f32);
+        let inertia = if !self.mass.is_finite() {
+            self.mass
+        } else if unit.mass > 0f32 {
+            unit.inertia * self.mass / unit.mass
+        } else {
+            // Without area, like for segments, the mass is spread evenly over the colliders.
+            // Collider counts are far too small to lose precision as a float
+            #[allow(clippy::cast_precision_loss)]
+            let part_mass = self.mass / (self.colliders.len() + 1) as f32;
+            self.parts()
+                .map(|(shape, offset, angle)| {
+                    let center = shape.center_of_mass();
+                    let placed = offset + center.rotate(angle);
+                    shape.moment_of_inertia(part_mass)
+                        + part_mass * (placed.length_squared() - center.length_squared())
+                })
+                .sum()
+        };
+        self.set_inertia(inertia);
+    }
+
+    /**
+     * Shape of the body and its colliders with their offsets and angles
+     */
+    fn parts(&self) -> impl Iterator<Item = (&Shape, Vec2<f32>, f32)> + '_ {
+        std::iter::once((&self.shape, Vec2::new(0f32, 0f32), 0f32)).chain(
+            self.colliders
+                .iter()
+                .map(|collider| (&collider.shape, collider.offset, collider.angle)),
+        )
+    }
+
+    /**
+     * Mass properties of the shape and colliders of the body combined
+     * for a density, the inertia is around the body position
+     * and the center of mass is relative to it
+     */
+    #[must_use]
+    pub fn mass_properties(&self, density: f32) -> MassProperties {
+        let mut total = MassProperties {
+            area:           0f32,
+            mass:           0f32,
+            center_of_mass: Vec2::new(0f32, 0f32),
+            inertia:        0f32,
+        };
+        // Sum of the part centers weighted by their area
+        let mut moment = Vec2::new(0f32, 0f32);
+        for (shape, offset, angle) in self.parts() {
+            let part = shape.mass_properties(density);
+            // Moves the inertia of the part from its own origin to the body position
+            let center = part.center_of_mass;
+            let placed = offset + center.rotate(angle);
+            total.inertia +=
+                part.inertia + part.mass * (placed.length_squared() - center.length_squared());
+            total.area += part.area;
+            total.mass += part.mass;
+            moment += placed * part.area;
         }
+        // Colliders without area, like segments, balance around the shape of the body
+        total.center_of_mass = if total.area > 0f32 {
+            moment / total.area
+        } else {
+            self.shape.center_of_mass()
+        };
+        total
+    }
+
+    /**
+     * Applies a world space force at a world space point,
+     * generating torque if the point is not the body position
+     */
+    pub fn apply_force_at_point(&mut self, force: Vec2<f32>, point: Vec2<f32>) {
+        self.force += force;
+        self.torque += (point - self.position).cross(&force);
+    }
+
+    /**
+     * Position between the previous and the current fixed step,
+     * `alpha` is the fraction of a step since the current one
+     */
+    #[must_use]
+    pub fn interpolated_position(&self, alpha: f32) -> Vec2<f32> {
+        self.previous_position + (self.position - self.previous_position) * alpha
+    }
+
+    /**
+     * Angle between the previous and the current fixed step
+     */
+    #[must_use]
+    pub fn interpolated_angle(&self, alpha: f32) -> f32 {
+        self.previous_angle + (self.angle - self.previous_angle) * alpha
     }
 
+    /**
+     * Whether the collision layers and groups of two bodies let them collide
+     */
+    #[must_use]
+    pub fn can_collide_with(&self, other: &Body) -> bool {
+        match (self.group, other.group) {
+            (Some(group), Some(other_group)) if group == other_group && group != 0 => group > 0,
+            _ => self.memberships & other.filter != 0 && other.memberships & self.filter != 0,
+        }
+    }
+
+    /**
+     * Bounding box around every collider of the body
+     */
     #[must_use]
     pub fn get_aabb(&self) -> AABB<i32> {
-        self.shape.get_aabb(self.position)
+        self.placed_colliders()
+            .skip(1)
+            .map(|collider| collider.shape.get_aabb(collider.position, collider.angle))
+            .fold(
+                self.shape.get_aabb(self.position, self.angle),
+                |aabb, other| AABB {
+                    min: Vec2::new(aabb.min.x.min(other.min.x), aabb.min.y.min(other.min.y)),
+                    max: Vec2::new(aabb.max.x.max(other.max.x), aabb.max.y.max(other.max.y)),
+                },
+            )
+    }
+
+    /**
+     * The shape of the body followed by its colliders, placed in world space
+     */
+    pub(crate) fn placed_colliders(&self) -> impl Iterator<Item = PlacedCollider<'_>> {
+        (0..=self.colliders.len()).filter_map(move |index| self.placed_collider(index))
+    }
+
+    pub(crate) fn placed_collider(&self, index: usize) -> Option<PlacedCollider<'_>> {
+        if index == 0 {
+            return Some(PlacedCollider {
+                index,
+                shape: &self.shape,
+                position: self.position,
+                angle: self.angle,
+                sensor: self.sensor,
+                restitution: self.restitution,
+                friction: self.friction,
+                static_friction: self.static_friction,
+            });
+        }
+        let collider = self.colliders.get(index - 1)?;
+        Some(PlacedCollider {
+            index,
+            shape: &collider.shape,
+            position: self.position + collider.offset.rotate(self.angle),
+            angle: self.angle + collider.angle,
+            sensor: self.sensor || collider.sensor,
+            restitution: collider.restitution,
+            friction: collider.friction,
+            static_friction: collider.static_friction,
+        })
     }
 }
@@ -1,59 +1,835 @@
+use std::convert::TryFrom;
+
 use crate::{
-    collision::Contact,
-    shape::{Circle, AABB},
+    collision::{ContactManifold, ContactPoint, RayIntersection},
+    shape::{Circle, Polygon, Shape, AABB},
     Vec2,
 };
 
+// Prefer the edges of a as reference unless b is clearly better,
+// keeps the reference face from flipping between steps
+const REFERENCE_TOLERANCE: f32 = 0.001;
+// Feature ids hold the side of the reference edge in bit 0, the incident edge
+// in bits 1 to 6, the reference edge in the second byte and whether the shapes
+// were flipped above. Contacts with a vertex set bit 7 and keep the vertex in bits 0 to 6
+const FLIPPED_FEATURE: u32 = 1 << 16;
+const VERTEX_FEATURE: u32 = 1 << 7;
+
 fn distance_squared(vec: Vec2<f32>) -> f32 {
     (vec.x).powi(2) + (vec.y).powi(2)
 }
 
-pub fn aabb_vs_aabb(a: &AABB<f32>, b: &AABB<f32>) -> Option<Contact<f32>> {
-    let pos_diff = b.min - a.min;
+/**
+ * Vertices and normals of an axis aligned rect centered on its position,
+ * in the same winding order as a `Polygon`
+ */
+fn rect_vertices(rect: Vec2<f32>) -> ([Vec2<f32>; 4], [Vec2<f32>; 4]) {
+    let x_extent = rect.x / 2f32;
+    let y_extent = rect.y / 2f32;
+    (
+        [
+            Vec2::new(-x_extent, -y_extent),
+            Vec2::new(x_extent, -y_extent),
+            Vec2::new(x_extent, y_extent),
+            Vec2::new(-x_extent, y_extent),
+        ],
+        [
+            Vec2::new(0f32, -1f32),
+            Vec2::new(1f32, 0f32),
+            Vec2::new(0f32, 1f32),
+            Vec2::new(-1f32, 0f32),
+        ],
+    )
+}
+
+/**
+ * Position and angle of b in the local space of a
+ */
+fn relative_transform(
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> (Vec2<f32>, f32) {
+    (
+        (b_position - a_position).rotate(-a_angle),
+        b_angle - a_angle,
+    )
+}
+
+fn transform(vectors: &[Vec2<f32>], position: Vec2<f32>, angle: f32) -> Vec<Vec2<f32>> {
+    vectors
+        .iter()
+        .map(|vector| vector.rotate(angle) + position)
+        .collect()
+}
+
+/**
+ * Moves a manifold computed in the local space of a shape
+ * at `position` rotated by `angle` into world space
+ */
+fn to_world(
+    manifold: ContactManifold<f32>,
+    position: Vec2<f32>,
+    angle: f32,
+) -> ContactManifold<f32> {
+    let mut points = manifold.points().iter().map(|point| ContactPoint {
+        position: point.position.rotate(angle) + position,
+        ..*point
+    });
+    let mut world_manifold =
+        ContactManifold::new(manifold.normal.rotate(angle), points.next().unwrap());
+    points.for_each(|point| world_manifold.push(point));
+    world_manifold
+}
+
+/**
+ * Manifold with a single point
+ */
+fn single_point(
+    normal: Vec2<f32>,
+    position: Vec2<f32>,
+    penetration_depth: f32,
+    feature: u32,
+) -> ContactManifold<f32> {
+    ContactManifold::new(normal, ContactPoint {
+        position,
+        penetration_depth,
+        feature,
+    })
+}
 
-    let penetration =
-        (((b.max - b.min) / 2.0 + b.min) + ((a.max - a.min) / 2.0 + a.min)) - pos_diff.abs();
-    if penetration.x <= 0f32 || penetration.y <= 0f32 {
+/**
+ * A convex vertex list with counter-clockwise vertices and outward edge normals,
+ * optionally rounded by a radius
+ */
+struct Convex<'a> {
+    vertices: &'a [Vec2<f32>],
+    normals:  &'a [Vec2<f32>],
+    radius:   f32,
+}
+
+impl Convex<'_> {
+    fn edge(&self, index: usize) -> (Vec2<f32>, Vec2<f32>) {
+        (
+            self.vertices[index],
+            self.vertices[(index + 1) % self.vertices.len()],
+        )
+    }
+}
+
+/**
+ * Finds the edge of `a` along which `b` is the most separated,
+ * returns the separation along that edge normal and the index of the edge.
+ * A negative separation means that `b` penetrates `a` along every edge.
+ */
+fn find_max_separation(a: &Convex, b: &Convex) -> (f32, usize) {
+    a.vertices.iter().zip(a.normals).enumerate().fold(
+        (f32::NEG_INFINITY, 0),
+        |(max_separation, max_edge), (edge, (vertex, normal))| {
+            let separation = b
+                .vertices
+                .iter()
+                .map(|b_vertex| normal.dot(&(*b_vertex - *vertex)))
+                .fold(f32::INFINITY, f32::min);
+            if separation > max_separation {
+                (separation, edge)
+            } else {
+                (max_separation, max_edge)
+            }
+        },
+    )
+}
+
+/**
+ * Packs the features that produced a contact point into an id.
+ * Whether a point was clipped is left out, as it changes back and forth
+ * for shapes of the same size resting on each other.
+ */
+fn feature_id(reference_edge: usize, incident_edge: usize, side: usize, flipped: bool) -> u32 {
+    let flipped = if flipped { FLIPPED_FEATURE } else { 0 };
+    flipped
+        | (feature_bits(reference_edge, 0xff) << 8)
+        | (feature_bits(incident_edge, 0x3f) << 1)
+        | feature_bits(side, 1)
+}
+
+/**
+ * Lowest bits of an index that fit in its part of a feature id
+ */
+fn feature_bits(index: usize, mask: u32) -> u32 {
+    u32::try_from(index & mask as usize).unwrap_or_default()
+}
+
+/**
+ * Clips the segment between two points to the half plane `normal . point <= offset`,
+ * returns `None` if both points are outside of the half plane
+ */
+fn clip_segment(points: [Vec2<f32>; 2], normal: Vec2<f32>, offset: f32) -> Option<[Vec2<f32>; 2]> {
+    let distance_0 = normal.dot(&points[0]) - offset;
+    let distance_1 = normal.dot(&points[1]) - offset;
+
+    if distance_0 <= 0f32 && distance_1 <= 0f32 {
+        return Some(points);
+    }
+    if distance_0 > 0f32 && distance_1 > 0f32 {
         return None;
     }
-    if penetration.x < penetration.y {
-        let sign_x = pos_diff.x.signum();
-        return Some(Contact {
-            penetration_depth: penetration.x * sign_x,
-            normal:            Vec2::new(sign_x, 0f32),
-        });
-    }
-    let sign_y = pos_diff.y.signum();
-    Some(Contact {
-        penetration_depth: penetration.y * sign_y,
-        normal:            Vec2::new(0f32, sign_y),
-    })
+
+    let t = distance_0 / (distance_0 - distance_1);
+    let intersection = points[0] + (points[1] - points[0]) * t;
+    if distance_0 > 0f32 {
+        Some([intersection, points[1]])
+    } else {
+        Some([points[0], intersection])
+    }
 }
 
-pub fn rect_vs_rect(
-    a: &Vec2<f32>,
-    b: &Vec2<f32>,
-    a_position: &Vec2<f32>,
-    b_position: &Vec2<f32>,
-) -> Option<Contact<f32>> {
-    let pos_diff = *b_position - *a_position;
-
-    let penetration = ((*b / 2.0 + *b_position) + (*a / 2.0 + *a_position)) - pos_diff.abs();
-    if penetration.x <= 0f32 || penetration.y <= 0f32 {
+/**
+ * Separating axis test between two convex shapes in the same space.
+ * The incident edge of one shape is clipped against the reference edge of
+ * the other, which gives up to two points for shapes resting on each other.
+ */
+fn convex_vs_convex(a: &Convex, b: &Convex) -> Option<ContactManifold<f32>> {
+    let radius = a.radius + b.radius;
+
+    let (a_separation, a_edge) = find_max_separation(a, b);
+    if a_separation > radius {
         return None;
     }
-    if penetration.x < penetration.y {
-        let sign_x = pos_diff.x.signum();
-        return Some(Contact {
-            penetration_depth: penetration.x * sign_x,
-            normal:            Vec2::new(sign_x, 0f32),
-        });
-    }
-    let sign_y = pos_diff.y.signum();
-    Some(Contact {
-        penetration_depth: penetration.y * sign_y,
-        normal:            Vec2::new(0f32, sign_y),
-    })
+    let (b_separation, b_edge) = find_max_separation(b, a);
+    if b_separation > radius {
+        return None;
+    }
+
+    if a_separation > 0f32 || b_separation > 0f32 {
+        // The cores are apart, only the radii reach each other
+        return rounded_convex_contact(a, b, a.normals[a_edge]);
+    }
+
+    let (reference, incident, reference_edge, flipped) =
+        if b_separation > a_separation + REFERENCE_TOLERANCE {
+            (b, a, b_edge, true)
+        } else {
+            (a, b, a_edge, false)
+        };
+    let reference_normal = reference.normals[reference_edge];
+    let (v1, v2) = reference.edge(reference_edge);
+
+    // The incident edge is the one facing the reference edge the most
+    let incident_edge = (0..incident.normals.len())
+        .min_by(|i, j| {
+            reference_normal
+                .dot(&incident.normals[*i])
+                .total_cmp(&reference_normal.dot(&incident.normals[*j]))
+        })
+        .unwrap();
+    let (i1, i2) = incident.edge(incident_edge);
+
+    // Clip the incident edge to the sides of the reference edge
+    let tangent = (v2 - v1).normalize();
+    let clipped = clip_segment([i1, i2], -tangent, -tangent.dot(&v1))
+        .and_then(|points| clip_segment(points, tangent, tangent.dot(&v2)))?;
+
+    let normal = if flipped {
+        -reference_normal
+    } else {
+        reference_normal
+    };
+    clipped
+        .iter()
+        .enumerate()
+        .filter_map(|(side, point)| {
+            let separation = reference_normal.dot(&(*point - v1));
+            if separation > radius {
+                return None;
+            }
+            // Halfway between the surfaces of the two shapes
+            let position = *point
+                + reference_normal * ((reference.radius - separation - incident.radius) / 2f32);
+            Some(ContactPoint {
+                position,
+                penetration_depth: radius - separation,
+                feature: feature_id(reference_edge, incident_edge, side, flipped),
+            })
+        })
+        .fold(None, |manifold: Option<ContactManifold<f32>>, point| {
+            Some(manifold.map_or_else(
+                || ContactManifold::new(normal, point),
+                |mut manifold| {
+                    manifold.push(point);
+                    manifold
+                },
+            ))
+        })
+}
+
+/**
+ * Contact between the closest edges of two rounded convex shapes
+ * whose cores do not overlap
+ */
+fn rounded_convex_contact(
+    a: &Convex,
+    b: &Convex,
+    fallback_normal: Vec2<f32>,
+) -> Option<ContactManifold<f32>> {
+    let (a_point, b_point) = (0..a.vertices.len())
+        .flat_map(|a_edge| (0..b.vertices.len()).map(move |b_edge| (a_edge, b_edge)))
+        .map(|(a_edge, b_edge)| {
+            let (a_start, a_end) = a.edge(a_edge);
+            let (b_start, b_end) = b.edge(b_edge);
+            closest_points_between_segments(a_start, a_end, b_start, b_end)
+        })
+        .min_by(|(a1, b1), (a2, b2)| {
+            distance_squared(*b1 - *a1).total_cmp(&distance_squared(*b2 - *a2))
+        })
+        .unwrap();
+    rounded_contact(a_point, b_point, a.radius, b.radius, fallback_normal)
+}
+
+/**
+ * Checks a convex vertex list against a circle
+ * whose center is given in the same space as the vertices
+ */
+fn convex_vs_circle(
+    vertices: &[Vec2<f32>],
+    normals: &[Vec2<f32>],
+    radius: f32,
+    center: Vec2<f32>,
+) -> Option<ContactManifold<f32>> {
+    let (separation, edge) = vertices.iter().zip(normals).enumerate().fold(
+        (f32::NEG_INFINITY, 0),
+        |(max_separation, max_edge), (edge, (vertex, normal))| {
+            let separation = normal.dot(&(center - *vertex));
+            if separation > max_separation {
+                (separation, edge)
+            } else {
+                (max_separation, max_edge)
+            }
+        },
+    );
+
+    if separation > radius {
+        return None;
+    }
+
+    // Contact points lie halfway into the overlap
+    let depth = radius - separation;
+    let face_contact = single_point(
+        normals[edge],
+        center - normals[edge] * (radius - depth / 2f32),
+        depth,
+        feature_bits(edge, 0x7f),
+    );
+
+    // Center is inside the polygon
+    if separation <= 0f32 {
+        return Some(face_contact);
+    }
+
+    let v1 = vertices[edge];
+    let v2 = vertices[(edge + 1) % vertices.len()];
+
+    let (closest, vertex) = if (center - v1).dot(&(v2 - v1)) <= 0f32 {
+        (v1, edge)
+    } else if (center - v2).dot(&(v1 - v2)) <= 0f32 {
+        (v2, (edge + 1) % vertices.len())
+    } else {
+        // Center is closest to the edge itself
+        return Some(face_contact);
+    };
+
+    let distance = center - closest;
+    let distance_sqr = distance_squared(distance);
+    if distance_sqr > radius * radius {
+        return None;
+    }
+    let distance_sqrt = distance_sqr.sqrt();
+    let normal = distance / distance_sqrt;
+
+    let depth = radius - distance_sqrt;
+    Some(single_point(
+        normal,
+        center - normal * (radius - depth / 2f32),
+        depth,
+        VERTEX_FEATURE | feature_bits(vertex, 0x7f),
+    ))
+}
+
+/**
+ * A line segment with a radius around it,
+ * the common representation of capsules and segments
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct RoundedSegment {
+    pub a:      Vec2<f32>,
+    pub b:      Vec2<f32>,
+    pub radius: f32,
+}
+
+impl RoundedSegment {
+    #[must_use]
+    pub fn capsule(half_length: f32, radius: f32) -> Self {
+        Self {
+            a: Vec2::new(0f32, -half_length),
+            b: Vec2::new(0f32, half_length),
+            radius,
+        }
+    }
+
+    #[must_use]
+    pub fn segment(a: Vec2<f32>, b: Vec2<f32>) -> Self {
+        Self { a, b, radius: 0f32 }
+    }
+
+    fn endpoints(&self, position: Vec2<f32>, angle: f32) -> (Vec2<f32>, Vec2<f32>) {
+        (
+            self.a.rotate(angle) + position,
+            self.b.rotate(angle) + position,
+        )
+    }
+}
+
+fn closest_point_on_segment(start: Vec2<f32>, end: Vec2<f32>, point: Vec2<f32>) -> Vec2<f32> {
+    let direction = end - start;
+    let length_sqr = direction.length_squared();
+    if length_sqr == 0f32 {
+        return start;
+    }
+    let t = ((point - start).dot(&direction) / length_sqr).clamp(0f32, 1f32);
+    start + direction * t
+}
+
+/**
+ * Closest points between the segments `a_start`-`a_end` and `b_start`-`b_end`,
+ * the returned points lie on a and b respectively
+ */
+fn closest_points_between_segments(
+    a_start: Vec2<f32>,
+    a_end: Vec2<f32>,
+    b_start: Vec2<f32>,
+    b_end: Vec2<f32>,
+) -> (Vec2<f32>, Vec2<f32>) {
+    let a_direction = a_end - a_start;
+    let b_direction = b_end - b_start;
+    let a_length_sqr = a_direction.length_squared();
+    let b_length_sqr = b_direction.length_squared();
+
+    if a_length_sqr == 0f32 {
+        return (a_start, closest_point_on_segment(b_start, b_end, a_start));
+    }
+    if b_length_sqr == 0f32 {
+        return (closest_point_on_segment(a_start, a_end, b_start), b_start);
+    }
+
+    let offset = a_start - b_start;
+    let a_offset = a_direction.dot(&offset);
+    let b_offset = b_direction.dot(&offset);
+    let directions = a_direction.dot(&b_direction);
+    let denominator = a_length_sqr * b_length_sqr - directions * directions;
+
+    // Parallel segments pick an arbitrary point on a
+    let mut a_t = if denominator == 0f32 {
+        0f32
+    } else {
+        ((directions * b_offset - a_offset * b_length_sqr) / denominator).clamp(0f32, 1f32)
+    };
+    let mut b_t = (directions * a_t + b_offset) / b_length_sqr;
+
+    if b_t < 0f32 {
+        b_t = 0f32;
+        a_t = (-a_offset / a_length_sqr).clamp(0f32, 1f32);
+    } else if b_t > 1f32 {
+        b_t = 1f32;
+        a_t = ((directions - a_offset) / a_length_sqr).clamp(0f32, 1f32);
+    }
+
+    (a_start + a_direction * a_t, b_start + b_direction * b_t)
+}
+
+/**
+ * Normal used when the cores of two rounded shapes touch,
+ * perpendicular to the segment and facing `towards` if possible
+ */
+fn fallback_normal(start: Vec2<f32>, end: Vec2<f32>, towards: Vec2<f32>) -> Vec2<f32> {
+    let normal = (end - start).perpendicular().normalize();
+    if normal.length_squared() == 0f32 {
+        let towards = towards.normalize();
+        if towards.length_squared() == 0f32 {
+            return Vec2::new(1f32, 0f32);
+        }
+        return towards;
+    }
+    if normal.dot(&towards) < 0f32 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+/**
+ * Contact between two rounded features given their closest core points,
+ * the normal points from `a_point` towards `b_point`
+ */
+fn rounded_contact(
+    a_point: Vec2<f32>,
+    b_point: Vec2<f32>,
+    a_radius: f32,
+    b_radius: f32,
+    fallback_normal: Vec2<f32>,
+) -> Option<ContactManifold<f32>> {
+    let distance = b_point - a_point;
+    let radius = a_radius + b_radius;
+    let distance_sqr = distance_squared(distance);
+    if distance_sqr > radius * radius {
+        return None;
+    }
+    let distance_sqrt = distance_sqr.sqrt();
+    let normal = if distance_sqrt == 0f32 {
+        fallback_normal
+    } else {
+        distance / distance_sqrt
+    };
+    let penetration_depth = radius - distance_sqrt;
+
+    Some(single_point(
+        normal,
+        a_point + normal * (a_radius - penetration_depth / 2f32),
+        penetration_depth,
+        0,
+    ))
+}
+
+/**
+ * Checks a convex vertex list against a rounded segment
+ * whose end points are given in the same space as the vertices
+ */
+fn convex_vs_segment(
+    vertices: &[Vec2<f32>],
+    normals: &[Vec2<f32>],
+    start: Vec2<f32>,
+    end: Vec2<f32>,
+    radius: f32,
+) -> Option<ContactManifold<f32>> {
+    let side = (end - start).perpendicular().normalize();
+    if side.length_squared() == 0f32 {
+        return convex_vs_circle(vertices, normals, radius, start);
+    }
+    convex_vs_convex(
+        &Convex {
+            vertices,
+            normals,
+            radius: 0f32,
+        },
+        &Convex {
+            vertices: &[start, end],
+            normals: &[-side, side],
+            radius,
+        },
+    )
+}
+
+/**
+ * Checks two shapes against each other,
+ * the contact normal points from a towards b
+ */
+pub fn shape_vs_shape(
+    a_shape: &Shape,
+    b_shape: &Shape,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    match (a_shape, b_shape) {
+        (
+            Shape::Capsule {
+                half_length,
+                radius,
+            },
+            _,
+        ) => capsule_vs_shape(
+            &RoundedSegment::capsule(*half_length, *radius),
+            b_shape,
+            a_position,
+            b_position,
+            a_angle,
+            b_angle,
+        ),
+        (Shape::Segment { a, b }, _) => capsule_vs_shape(
+            &RoundedSegment::segment(*a, *b),
+            b_shape,
+            a_position,
+            b_position,
+            a_angle,
+            b_angle,
+        ),
+        (
+            _,
+            Shape::Capsule {
+                half_length,
+                radius,
+            },
+        ) => capsule_vs_shape(
+            &RoundedSegment::capsule(*half_length, *radius),
+            a_shape,
+            b_position,
+            a_position,
+            b_angle,
+            a_angle,
+        )
+        .map(ContactManifold::inverted),
+        (_, Shape::Segment { a, b }) => capsule_vs_shape(
+            &RoundedSegment::segment(*a, *b),
+            a_shape,
+            b_position,
+            a_position,
+            b_angle,
+            a_angle,
+        )
+        .map(ContactManifold::inverted),
+        (Shape::Circle(a_circle), Shape::Circle(b_circle)) => {
+            circle_vs_circle(a_circle, b_circle, a_position, b_position)
+        },
+        (Shape::Rect(a_rect), Shape::Rect(b_rect)) => {
+            rect_vs_rect(*a_rect, *b_rect, a_position, b_position, a_angle, b_angle)
+        },
+        (Shape::Rect(rect), Shape::Circle(circle)) => {
+            rect_vs_circle(*rect, circle, a_position, b_position, a_angle)
+        },
+        (Shape::Circle(circle), Shape::Rect(rect)) => {
+            rect_vs_circle(*rect, circle, b_position, a_position, b_angle)
+                .map(ContactManifold::inverted)
+        },
+        (Shape::Polygon(a_polygon), Shape::Polygon(b_polygon)) => polygon_vs_polygon(
+            a_polygon, b_polygon, a_position, b_position, a_angle, b_angle,
+        ),
+        (Shape::Polygon(polygon), Shape::Rect(rect)) => {
+            polygon_vs_rect(polygon, *rect, a_position, b_position, a_angle, b_angle)
+        },
+        (Shape::Rect(rect), Shape::Polygon(polygon)) => {
+            polygon_vs_rect(polygon, *rect, b_position, a_position, b_angle, a_angle)
+                .map(ContactManifold::inverted)
+        },
+        (Shape::Polygon(polygon), Shape::Circle(circle)) => {
+            polygon_vs_circle(polygon, circle, a_position, b_position, a_angle)
+        },
+        (Shape::Circle(circle), Shape::Polygon(polygon)) => {
+            polygon_vs_circle(polygon, circle, b_position, a_position, b_angle)
+                .map(ContactManifold::inverted)
+        },
+    }
+}
+
+/**
+ * Checks a capsule or segment against any shape
+ */
+fn capsule_vs_shape(
+    a_segment: &RoundedSegment,
+    b_shape: &Shape,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    match b_shape {
+        Shape::Circle(circle) => {
+            capsule_vs_circle(a_segment, circle, a_position, b_position, a_angle)
+        },
+        Shape::Rect(rect) => {
+            rect_vs_capsule(*rect, a_segment, b_position, a_position, b_angle, a_angle)
+                .map(ContactManifold::inverted)
+        },
+        Shape::Polygon(polygon) => {
+            polygon_vs_capsule(polygon, a_segment, b_position, a_position, b_angle, a_angle)
+                .map(ContactManifold::inverted)
+        },
+        Shape::Capsule {
+            half_length,
+            radius,
+        } => capsule_vs_capsule(
+            a_segment,
+            &RoundedSegment::capsule(*half_length, *radius),
+            a_position,
+            b_position,
+            a_angle,
+            b_angle,
+        ),
+        Shape::Segment { a, b } => capsule_vs_capsule(
+            a_segment,
+            &RoundedSegment::segment(*a, *b),
+            a_position,
+            b_position,
+            a_angle,
+            b_angle,
+        ),
+    }
+}
+
+pub fn capsule_vs_circle(
+    a_segment: &RoundedSegment,
+    b_circle: &Circle,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (start, end) = a_segment.endpoints(a_position, a_angle);
+    rounded_contact(
+        closest_point_on_segment(start, end, b_position),
+        b_position,
+        a_segment.radius,
+        b_circle.radius,
+        fallback_normal(start, end, b_position - a_position),
+    )
+}
+
+pub fn capsule_vs_capsule(
+    a_segment: &RoundedSegment,
+    b_segment: &RoundedSegment,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (a_start, a_end) = a_segment.endpoints(a_position, a_angle);
+    let (b_start, b_end) = b_segment.endpoints(b_position, b_angle);
+    let (a_point, b_point) = closest_points_between_segments(a_start, a_end, b_start, b_end);
+    rounded_contact(
+        a_point,
+        b_point,
+        a_segment.radius,
+        b_segment.radius,
+        fallback_normal(a_start, a_end, b_position - a_position),
+    )
+}
+
+pub fn polygon_vs_capsule(
+    a_polygon: &Polygon,
+    b_segment: &RoundedSegment,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (position, angle) = relative_transform(a_position, b_position, a_angle, b_angle);
+    let (start, end) = b_segment.endpoints(position, angle);
+    convex_vs_segment(
+        &a_polygon.vertices,
+        &a_polygon.normals,
+        start,
+        end,
+        b_segment.radius,
+    )
+    .map(|manifold| to_world(manifold, a_position, a_angle))
+}
+
+pub fn rect_vs_capsule(
+    a_rect: Vec2<f32>,
+    b_segment: &RoundedSegment,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (position, angle) = relative_transform(a_position, b_position, a_angle, b_angle);
+    let (start, end) = b_segment.endpoints(position, angle);
+    let (vertices, normals) = rect_vertices(a_rect);
+    convex_vs_segment(&vertices, &normals, start, end, b_segment.radius)
+        .map(|manifold| to_world(manifold, a_position, a_angle))
+}
+
+pub fn polygon_vs_polygon(
+    a_polygon: &Polygon,
+    b_polygon: &Polygon,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (position, angle) = relative_transform(a_position, b_position, a_angle, b_angle);
+    convex_vs_convex(
+        &Convex {
+            vertices: &a_polygon.vertices,
+            normals:  &a_polygon.normals,
+            radius:   0f32,
+        },
+        &Convex {
+            vertices: &transform(&b_polygon.vertices, position, angle),
+            normals:  &transform(&b_polygon.normals, Vec2::new(0f32, 0f32), angle),
+            radius:   0f32,
+        },
+    )
+    .map(|manifold| to_world(manifold, a_position, a_angle))
+}
+
+pub fn polygon_vs_rect(
+    a_polygon: &Polygon,
+    b_rect: Vec2<f32>,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (position, angle) = relative_transform(a_position, b_position, a_angle, b_angle);
+    let (b_vertices, b_normals) = rect_vertices(b_rect);
+    convex_vs_convex(
+        &Convex {
+            vertices: &a_polygon.vertices,
+            normals:  &a_polygon.normals,
+            radius:   0f32,
+        },
+        &Convex {
+            vertices: &transform(&b_vertices, position, angle),
+            normals:  &transform(&b_normals, Vec2::new(0f32, 0f32), angle),
+            radius:   0f32,
+        },
+    )
+    .map(|manifold| to_world(manifold, a_position, a_angle))
+}
+
+pub fn polygon_vs_circle(
+    a_polygon: &Polygon,
+    b_circle: &Circle,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    convex_vs_circle(
+        &a_polygon.vertices,
+        &a_polygon.normals,
+        b_circle.radius,
+        (b_position - a_position).rotate(-a_angle),
+    )
+    .map(|manifold| to_world(manifold, a_position, a_angle))
+}
+
+/**
+ * Separating axis test between two oriented rects centered on their positions
+ */
+pub fn rect_vs_rect(
+    a_rect: Vec2<f32>,
+    b_rect: Vec2<f32>,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (position, angle) = relative_transform(a_position, b_position, a_angle, b_angle);
+    let (a_vertices, a_normals) = rect_vertices(a_rect);
+    let (b_vertices, b_normals) = rect_vertices(b_rect);
+    convex_vs_convex(
+        &Convex {
+            vertices: &a_vertices,
+            normals:  &a_normals,
+            radius:   0f32,
+        },
+        &Convex {
+            vertices: &transform(&b_vertices, position, angle),
+            normals:  &transform(&b_normals, Vec2::new(0f32, 0f32), angle),
+            radius:   0f32,
+        },
+    )
+    .map(|manifold| to_world(manifold, a_position, a_angle))
 }
 
 pub fn circle_vs_circle(
@@ -61,7 +837,7 @@ pub fn circle_vs_circle(
     b_circle: &Circle,
     a_position: Vec2<f32>,
     b_position: Vec2<f32>,
-) -> Option<Contact<f32>> {
+) -> Option<ContactManifold<f32>> {
     let distance = b_position - a_position;
 
     let radius = (a_circle.radius + b_circle.radius).powi(2);
@@ -75,74 +851,877 @@ pub fn circle_vs_circle(
     let distance_sqrt = distance_sqr.sqrt();
 
     if distance_sqrt != 0f32 {
-        return Some(Contact {
-            penetration_depth: (a_circle.radius + b_circle.radius) - distance_sqrt,
-            normal:            distance / distance_sqrt,
-        });
+        let penetration_depth = (a_circle.radius + b_circle.radius) - distance_sqrt;
+        let normal = distance / distance_sqrt;
+        return Some(single_point(
+            normal,
+            a_position + normal * (a_circle.radius - penetration_depth / 2f32),
+            penetration_depth,
+            0,
+        ));
     }
     // Circles are on the same position
     // Choose random (but consistent) values
-    Some(Contact {
-        penetration_depth: a_circle.radius.min(b_circle.radius),
-        normal:            Vec2::new(1f32, 0f32),
-    })
+    Some(single_point(
+        Vec2::new(1f32, 0f32),
+        a_position,
+        a_circle.radius.min(b_circle.radius),
+        0,
+    ))
 }
 
+/**
+ * Checks an oriented rect centered on its position against a circle
+ */
 pub fn rect_vs_circle(
-    a_rect: &Vec2<f32>,
+    a_rect: Vec2<f32>,
     b_circle: &Circle,
-    a_position: &Vec2<f32>,
-    b_position: &Vec2<f32>,
-) -> Option<Contact<f32>> {
-    let normal = *b_position - *a_position;
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+) -> Option<ContactManifold<f32>> {
+    let (vertices, normals) = rect_vertices(a_rect);
+    convex_vs_circle(
+        &vertices,
+        &normals,
+        b_circle.radius,
+        (b_position - a_position).rotate(-a_angle),
+    )
+    .map(|manifold| to_world(manifold, a_position, a_angle))
+}
 
-    let x_extent = a_rect.x / 2f32;
-    let y_extent = a_rect.y / 2f32;
+/**
+ * Whether a world space point lies inside a shape
+ */
+pub fn point_in_shape(point: Vec2<f32>, shape: &Shape, position: Vec2<f32>, angle: f32) -> bool {
+    let local = (point - position).rotate(-angle);
+    match shape {
+        Shape::Circle(circle) => local.length_squared() <= circle.radius * circle.radius,
+        Shape::Rect(rect) => local.x.abs() <= rect.x / 2f32 && local.y.abs() <= rect.y / 2f32,
+        Shape::Polygon(polygon) => polygon
+            .vertices
+            .iter()
+            .zip(&polygon.normals)
+            .all(|(vertex, normal)| normal.dot(&(local - *vertex)) <= 0f32),
+        Shape::Capsule {
+            half_length,
+            radius,
+        } => {
+            let segment = RoundedSegment::capsule(*half_length, *radius);
+            (local - closest_point_on_segment(segment.a, segment.b, local)).length_squared()
+                <= radius * radius
+        },
+        Shape::Segment { a, b } => closest_point_on_segment(*a, *b, local) == local,
+    }
+}
 
-    let mut closest = Vec2::new(
-        f32::clamp(normal.x, -x_extent, x_extent),
-        f32::clamp(normal.y, -y_extent, y_extent),
-    );
+/**
+ * Hit of a ray that starts inside a shape,
+ * the normal faces against the ray
+ */
+fn ray_inside(direction: Vec2<f32>) -> RayIntersection<f32> {
+    RayIntersection {
+        toi:    0f32,
+        normal: -direction.normalize(),
+    }
+}
 
-    let mut inside = false;
+/**
+ * Clips a ray against the edge planes of a convex vertex list,
+ * the ray enters the shape at the last plane it crosses inwards
+ */
+fn ray_vs_convex(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    vertices: &[Vec2<f32>],
+    normals: &[Vec2<f32>],
+) -> Option<RayIntersection<f32>> {
+    let mut enter = 0f32;
+    let mut exit = max_toi;
+    let mut enter_normal = None;
 
-    // Circle is inside the AABB
-    if normal == closest {
-        inside = true;
-        // finds the closest axis
-        if normal.x.abs() > normal.y.abs() {
-            if closest.x > 0f32 {
-                closest.x = x_extent;
-            } else {
-                closest.x = -x_extent;
+    for (vertex, normal) in vertices.iter().zip(normals) {
+        let distance = normal.dot(&(*vertex - origin));
+        let speed = normal.dot(&direction);
+        if speed == 0f32 {
+            // Parallel to the edge, misses unless it is on the inner side
+            if distance < 0f32 {
+                return None;
+            }
+            continue;
+        }
+
+        let toi = distance / speed;
+        if speed < 0f32 {
+            if toi > enter {
+                enter = toi;
+                enter_normal = Some(*normal);
             }
-        } else if closest.y > 0f32 {
-            closest.y = y_extent;
         } else {
-            closest.y = -y_extent;
+            exit = exit.min(toi);
+        }
+        if exit < enter {
+            return None;
         }
     }
 
-    let distance = distance_squared(normal - closest);
-    let radius = b_circle.radius;
+    Some(enter_normal.map_or_else(
+        || ray_inside(direction),
+        |normal| RayIntersection { toi: enter, normal },
+    ))
+}
+
+/**
+ * Entry time of impact of a ray into a bounding box,
+ * used to skip whatever the box encloses
+ */
+pub fn ray_vs_aabb(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    aabb: &AABB<i32>,
+) -> Option<f32> {
+    // Bounds of the broad phase are far too small to lose precision as floats
+    #[allow(clippy::cast_precision_loss)]
+    let (min, max) = (
+        Vec2::new(aabb.min.x as f32, aabb.min.y as f32),
+        Vec2::new(aabb.max.x as f32, aabb.max.y as f32),
+    );
+    let mut enter = 0f32;
+    let mut exit = max_toi;
+
+    for (origin, direction, min, max) in [
+        (origin.x, direction.x, min.x, max.x),
+        (origin.y, direction.y, min.y, max.y),
+    ] {
+        if direction == 0f32 {
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+        let (near, far) = ((min - origin) / direction, (max - origin) / direction);
+        enter = enter.max(near.min(far));
+        exit = exit.min(near.max(far));
+        if exit < enter {
+            return None;
+        }
+    }
+
+    Some(enter)
+}
 
-    // Return none if radius is shorter than distance to closest
-    // point and circle not inside AABB
-    if distance > (radius * radius) && !inside {
+pub fn ray_vs_circle(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    circle: &Circle,
+    position: Vec2<f32>,
+) -> Option<RayIntersection<f32>> {
+    let offset = origin - position;
+    let distance = offset.length_squared() - circle.radius * circle.radius;
+    if distance <= 0f32 {
+        return Some(ray_inside(direction));
+    }
+    let speed = offset.dot(&direction);
+    if speed >= 0f32 {
+        // Outside and moving away
         return None;
     }
 
-    let distance_sqr = distance.sqrt();
+    let direction_squared = direction.length_squared();
+    let discriminant = speed * speed - direction_squared * distance;
+    if discriminant < 0f32 {
+        return None;
+    }
+    let toi = (-speed - discriminant.sqrt()) / direction_squared;
+    if toi > max_toi {
+        return None;
+    }
+    Some(RayIntersection {
+        toi,
+        normal: (offset + direction * toi).normalize(),
+    })
+}
 
-    if inside {
-        Some(Contact {
-            penetration_depth: (radius - distance_sqr),
-            normal:            normal / radius,
-        })
-    } else {
-        Some(Contact {
-            penetration_depth: (radius - distance_sqr),
-            normal:            normal / distance,
-        })
+pub fn ray_vs_rect(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    rect: Vec2<f32>,
+    position: Vec2<f32>,
+    angle: f32,
+) -> Option<RayIntersection<f32>> {
+    let (vertices, normals) = rect_vertices(rect);
+    ray_vs_convex(
+        (origin - position).rotate(-angle),
+        direction.rotate(-angle),
+        max_toi,
+        &vertices,
+        &normals,
+    )
+    .map(|intersection| RayIntersection {
+        normal: intersection.normal.rotate(angle),
+        ..intersection
+    })
+}
+
+pub fn ray_vs_polygon(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    polygon: &Polygon,
+    position: Vec2<f32>,
+    angle: f32,
+) -> Option<RayIntersection<f32>> {
+    ray_vs_convex(
+        (origin - position).rotate(-angle),
+        direction.rotate(-angle),
+        max_toi,
+        &polygon.vertices,
+        &polygon.normals,
+    )
+    .map(|intersection| RayIntersection {
+        normal: intersection.normal.rotate(angle),
+        ..intersection
+    })
+}
+
+/**
+ * A rounded segment is the rect swept along the segment
+ * together with a circle at both of its ends
+ */
+pub fn ray_vs_rounded_segment(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    segment: &RoundedSegment,
+    position: Vec2<f32>,
+    angle: f32,
+) -> Option<RayIntersection<f32>> {
+    let (start, end) = segment.endpoints(position, angle);
+    let circle = Circle::new(segment.radius);
+    // Without an axis there is no core, only the rounded end
+    if start == end {
+        if segment.radius == 0f32 {
+            return None;
+        }
+        return ray_vs_circle(origin, direction, max_toi, &circle, start);
+    }
+    let axis = (end - start).normalize();
+    let side = axis.perpendicular() * segment.radius;
+    let core = ray_vs_convex(
+        origin,
+        direction,
+        max_toi,
+        &[start - side, end - side, end + side, start + side],
+        &[-axis.perpendicular(), axis, axis.perpendicular(), -axis],
+    );
+    if segment.radius == 0f32 {
+        return core;
+    }
+
+    [
+        core,
+        ray_vs_circle(origin, direction, max_toi, &circle, start),
+        ray_vs_circle(origin, direction, max_toi, &circle, end),
+    ]
+    .iter()
+    .flatten()
+    .copied()
+    .min_by(|a, b| a.toi.total_cmp(&b.toi))
+}
+
+pub fn ray_vs_shape(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    shape: &Shape,
+    position: Vec2<f32>,
+    angle: f32,
+) -> Option<RayIntersection<f32>> {
+    match shape {
+        Shape::Circle(circle) => ray_vs_circle(origin, direction, max_toi, circle, position),
+        Shape::Rect(rect) => ray_vs_rect(origin, direction, max_toi, *rect, position, angle),
+        Shape::Polygon(polygon) => {
+            ray_vs_polygon(origin, direction, max_toi, polygon, position, angle)
+        },
+        Shape::Capsule {
+            half_length,
+            radius,
+        } => ray_vs_rounded_segment(
+            origin,
+            direction,
+            max_toi,
+            &RoundedSegment::capsule(*half_length, *radius),
+            position,
+            angle,
+        ),
+        Shape::Segment { a, b } => ray_vs_rounded_segment(
+            origin,
+            direction,
+            max_toi,
+            &RoundedSegment::segment(*a, *b),
+            position,
+            angle,
+        ),
+    }
+}
+
+/**
+ * Convex core of a shape in world space and the radius rounding it
+ */
+fn rounded_core(shape: &Shape, position: Vec2<f32>, angle: f32) -> (Vec<Vec2<f32>>, f32) {
+    match shape {
+        Shape::Circle(circle) => (vec![position], circle.radius),
+        Shape::Rect(rect) => (transform(&rect_vertices(*rect).0, position, angle), 0f32),
+        Shape::Polygon(polygon) => (transform(&polygon.vertices, position, angle), 0f32),
+        Shape::Capsule {
+            half_length,
+            radius,
+        } => {
+            let (start, end) =
+                RoundedSegment::capsule(*half_length, *radius).endpoints(position, angle);
+            (vec![start, end], *radius)
+        },
+        Shape::Segment { a, b } => (transform(&[*a, *b], position, angle), 0f32),
+    }
+}
+
+/**
+ * Counter-clockwise convex hull of a set of points without collinear points,
+ * has fewer than three points if the points are all on a line
+ */
+fn convex_hull(mut points: Vec<Vec2<f32>>) -> Vec<Vec2<f32>> {
+    points.sort_unstable_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut hull: Vec<Vec2<f32>> = Vec::with_capacity(points.len() + 1);
+    // The lower half from left to right, then the upper half back
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Box<dyn Iterator<Item = &Vec2<f32>>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for point in ordered {
+            while hull.len() >= start + 2 {
+                let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+                if (b - a).cross(&(*point - a)) > 0f32 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        // The last point is the first one of the other half
+        hull.pop();
+    }
+    hull
+}
+
+/**
+ * Ray against a convex hull rounded by a radius, the union
+ * of the hull and every edge swept into a capsule
+ */
+fn ray_vs_rounded_hull(
+    origin: Vec2<f32>,
+    direction: Vec2<f32>,
+    max_toi: f32,
+    hull: &[Vec2<f32>],
+    radius: f32,
+) -> Option<RayIntersection<f32>> {
+    match hull.len() {
+        0 => None,
+        1 => ray_vs_circle(origin, direction, max_toi, &Circle::new(radius), hull[0]),
+        2 => ray_vs_rounded_segment(
+            origin,
+            direction,
+            max_toi,
+            &RoundedSegment {
+                a: hull[0],
+                b: hull[1],
+                radius,
+            },
+            Vec2::new(0f32, 0f32),
+            0f32,
+        ),
+        _ => {
+            let normals: Vec<Vec2<f32>> = hull
+                .iter()
+                .zip(hull.iter().cycle().skip(1))
+                .map(|(a, b)| {
+                    let edge = *b - *a;
+                    Vec2::new(edge.y, -edge.x).normalize()
+                })
+                .collect();
+            let core = ray_vs_convex(origin, direction, max_toi, hull, &normals);
+            if radius == 0f32 {
+                return core;
+            }
+            hull.iter()
+                .zip(hull.iter().cycle().skip(1))
+                .map(|(a, b)| {
+                    ray_vs_rounded_segment(
+                        origin,
+                        direction,
+                        max_toi,
+                        &RoundedSegment {
+                            a: *a,
+                            b: *b,
+                            radius,
+                        },
+                        Vec2::new(0f32, 0f32),
+                        0f32,
+                    )
+                })
+                .chain(std::iter::once(core))
+                .flatten()
+                .min_by(|a, b| a.toi.total_cmp(&b.toi))
+        },
+    }
+}
+
+/**
+ * Moves shape a along `translation` until it hits shape b,
+ * the time of impact is the fraction of the translation before the hit
+ * and the normal points out of b.
+ *
+ * Casts a ray from the position of a against the Minkowski difference
+ * of both shapes, which is the rounded hull of the differences of their cores
+ */
+pub fn sweep_shape_vs_shape(
+    a_shape: &Shape,
+    b_shape: &Shape,
+    a_position: Vec2<f32>,
+    b_position: Vec2<f32>,
+    a_angle: f32,
+    b_angle: f32,
+    translation: Vec2<f32>,
+) -> Option<RayIntersection<f32>> {
+    let (a_core, a_radius) = rounded_core(a_shape, Vec2::new(0f32, 0f32), a_angle);
+    let (b_core, b_radius) = rounded_core(b_shape, b_position, b_angle);
+    let differences = b_core
+        .iter()
+        .flat_map(|b| a_core.iter().map(move |a| *b - *a))
+        .collect();
+
+    ray_vs_rounded_hull(
+        a_position,
+        translation,
+        1f32,
+        &convex_hull(differences),
+        a_radius + b_radius,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(size: f32) -> Polygon {
+        let half = size / 2f32;
+        Polygon::new(vec![
+            Vec2::new(-half, -half),
+            Vec2::new(-half, half),
+            Vec2::new(half, half),
+            Vec2::new(half, -half),
+        ])
+    }
+
+    #[test]
+    fn it_separates_polygons() {
+        let a = square(2.0);
+        let b = square(2.0);
+
+        assert!(
+            polygon_vs_polygon(&a, &b, Vec2::new(0.0, 0.0), Vec2::new(2.5, 0.0), 0.0, 0.0)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn it_finds_polygon_penetration_axis() {
+        let a = square(2.0);
+        let triangle = Polygon::new(vec![
+            Vec2::new(-1.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(0.0, 1.0),
+        ]);
+
+        let manifold = polygon_vs_polygon(
+            &a,
+            &triangle,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 0.75),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+
+        assert!((manifold.penetration_depth() - 0.25).abs() < 1e-5);
+        assert_eq!(manifold.normal, Vec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn it_collides_polygon_with_rect_and_circle() {
+        let a = square(2.0);
+
+        let manifold = polygon_vs_rect(
+            &a,
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(-1.5, 0.0),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+        assert!((manifold.penetration_depth() - 0.5).abs() < 1e-5);
+        assert_eq!(manifold.normal, Vec2::new(-1.0, 0.0));
+
+        let corner = Vec2::new(1.5, 1.5);
+        let manifold =
+            polygon_vs_circle(&a, &Circle::new(1.0), Vec2::new(0.0, 0.0), corner, 0.0).unwrap();
+        let expected_depth = 1.0 - 0.5f32.hypot(0.5);
+        assert!((manifold.penetration_depth() - expected_depth).abs() < 1e-5);
+        assert!((manifold.normal.x - manifold.normal.y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn it_collides_oriented_rects() {
+        let rect = Vec2::new(2.0, 2.0);
+        let quarter_turn = std::f32::consts::FRAC_PI_4;
+
+        // The rotated corner of b reaches sqrt(2) from its center
+        assert!(rect_vs_rect(
+            rect,
+            rect,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.2, 0.0),
+            0.0,
+            quarter_turn
+        )
+        .is_some());
+        // Axis aligned rects would overlap at the corners
+        assert!(rect_vs_rect(
+            rect,
+            rect,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.8, 1.8),
+            0.0,
+            quarter_turn
+        )
+        .is_none());
+
+        let manifold = rect_vs_rect(
+            rect,
+            rect,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(-2.0, 0.0),
+            0.0,
+            quarter_turn,
+        )
+        .unwrap();
+        assert!((manifold.penetration_depth() - (2f32.sqrt() - 1.0)).abs() < 1e-5);
+        assert!((manifold.normal.x + 1.0).abs() < 1e-5);
+        assert!(
+            (manifold.points()[0].position.x + 1.0 - manifold.penetration_depth() / 2.0).abs()
+                < 1e-5
+        );
+    }
+
+    #[test]
+    fn it_collides_oriented_rect_with_circle() {
+        let rect = Vec2::new(4.0, 1.0);
+        let circle = Circle::new(0.5);
+        let half_turn = std::f32::consts::FRAC_PI_2;
+
+        // Unrotated the rect reaches x = 2, rotated it only reaches x = 0.5
+        assert!(
+            rect_vs_circle(rect, &circle, Vec2::new(0.0, 0.0), Vec2::new(1.5, 0.0), 0.0).is_some()
+        );
+        assert!(rect_vs_circle(
+            rect,
+            &circle,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.5, 0.0),
+            half_turn
+        )
+        .is_none());
+
+        let manifold = rect_vs_circle(
+            rect,
+            &circle,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 2.25),
+            half_turn,
+        )
+        .unwrap();
+        assert!((manifold.penetration_depth() - 0.25).abs() < 1e-5);
+        assert!((manifold.normal.y - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn it_collides_capsules() {
+        let capsule = Shape::Capsule {
+            half_length: 1.0,
+            radius:      0.5,
+        };
+
+        let manifold = shape_vs_shape(
+            &capsule,
+            &Shape::Circle(Circle::new(0.5)),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.8, 0.5),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+        assert!((manifold.penetration_depth() - 0.2).abs() < 1e-5);
+        assert!((manifold.normal.x - 1.0).abs() < 1e-5);
+
+        // Lying down on top of a rect
+        let manifold = shape_vs_shape(
+            &Shape::Rect(Vec2::new(2.0, 2.0)),
+            &capsule,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 1.3),
+            0.0,
+            std::f32::consts::FRAC_PI_2,
+        )
+        .unwrap();
+        assert!((manifold.penetration_depth() - 0.2).abs() < 1e-5);
+        assert!((manifold.normal.y - 1.0).abs() < 1e-5);
+
+        // Standing next to a corner, only the rounded end reaches it
+        assert!(shape_vs_shape(
+            &Shape::Rect(Vec2::new(2.0, 2.0)),
+            &capsule,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.4, 2.4),
+            0.0,
+            0.0,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn it_collides_segments() {
+        let segment = Shape::Segment {
+            a: Vec2::new(-2.0, 0.0),
+            b: Vec2::new(2.0, 0.0),
+        };
+
+        let manifold = shape_vs_shape(
+            &Shape::Circle(Circle::new(1.0)),
+            &segment,
+            Vec2::new(0.5, 0.75),
+            Vec2::new(0.0, 0.0),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+        assert!((manifold.penetration_depth() - 0.25).abs() < 1e-5);
+        assert!((manifold.normal.y + 1.0).abs() < 1e-5);
+
+        let manifold = shape_vs_shape(
+            &Shape::Polygon(square(2.0)),
+            &segment,
+            Vec2::new(0.0, 0.8),
+            Vec2::new(0.0, 0.0),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+        assert!((manifold.penetration_depth() - 0.2).abs() < 1e-5);
+        assert!((manifold.normal.y + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn it_finds_two_points_for_resting_boxes() {
+        let manifold = rect_vs_rect(
+            Vec2::new(10.0, 2.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.5, 1.9),
+            0.0,
+            0.0,
+        )
+        .unwrap();
+
+        assert_eq!(manifold.points().len(), 2);
+        assert_eq!(manifold.normal, Vec2::new(0.0, 1.0));
+        let mut xs: Vec<f32> = manifold
+            .points()
+            .iter()
+            .map(|point| {
+                assert!((point.penetration_depth - 0.1).abs() < 1e-5);
+                assert!((point.position.y - 0.95).abs() < 1e-5);
+                point.position.x
+            })
+            .collect();
+        xs.sort_by(f32::total_cmp);
+        assert!((xs[0] + 0.5).abs() < 1e-5);
+        assert!((xs[1] - 1.5).abs() < 1e-5);
+        assert_ne!(manifold.points()[0].feature, manifold.points()[1].feature);
+    }
+
+    #[test]
+    fn it_casts_rays_against_circles() {
+        let circle = Circle::new(1.0);
+        let origin = Vec2::new(0.0, 0.0);
+
+        let hit = ray_vs_circle(
+            origin,
+            Vec2::new(2.0, 0.0),
+            10.0,
+            &circle,
+            Vec2::new(5.0, 0.0),
+        )
+        .unwrap();
+        assert!((hit.toi - 2.0).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+        assert!(ray_vs_circle(
+            origin,
+            Vec2::new(1.0, 0.0),
+            3.0,
+            &circle,
+            Vec2::new(5.0, 0.0)
+        )
+        .is_none());
+        assert!(ray_vs_circle(
+            origin,
+            Vec2::new(-1.0, 0.0),
+            10.0,
+            &circle,
+            Vec2::new(5.0, 0.0)
+        )
+        .is_none());
+        assert!(
+            ray_vs_circle(origin, Vec2::new(1.0, 0.0), 10.0, &circle, origin)
+                .unwrap()
+                .toi
+                .abs()
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn it_casts_rays_against_rotated_rects() {
+        let rect = Vec2::new(2.0, 2.0);
+        let position = Vec2::new(5.0, 0.0);
+        let angle = std::f32::consts::FRAC_PI_4;
+
+        let hit = ray_vs_rect(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            10.0,
+            rect,
+            position,
+            angle,
+        )
+        .unwrap();
+        // The corner of the rect points towards the ray
+        assert!((hit.toi - (5.0 - 2f32.sqrt())).abs() < 1e-5);
+
+        let hit = ray_vs_rect(
+            Vec2::new(5.0, 5.0),
+            Vec2::new(0.0, -1.0),
+            10.0,
+            rect,
+            position,
+            0.0,
+        )
+        .unwrap();
+        assert!((hit.toi - 4.0).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::new(0.0, 1.0));
+
+        assert!(ray_vs_rect(
+            Vec2::new(0.0, 2.0),
+            Vec2::new(1.0, 0.0),
+            10.0,
+            rect,
+            position,
+            0.0,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn it_casts_rays_against_degenerate_capsules() {
+        let capsule = RoundedSegment::capsule(0.0, 1.0);
+        let position = Vec2::new(5.0, 0.0);
+
+        assert!(ray_vs_rounded_segment(
+            Vec2::new(0.0, 2.0),
+            Vec2::new(1.0, 0.0),
+            10.0,
+            &capsule,
+            position,
+            0.0,
+        )
+        .is_none());
+
+        let hit = ray_vs_rounded_segment(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            10.0,
+            &capsule,
+            position,
+            0.0,
+        )
+        .unwrap();
+        assert!((hit.toi - 4.0).abs() < 1e-5);
+
+        assert!(ray_vs_rounded_segment(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            10.0,
+            &RoundedSegment::segment(position, position),
+            Vec2::new(0.0, 0.0),
+            0.0,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn it_sweeps_circles_and_capsules() {
+        let circle = Shape::Circle(Circle::new(1.0));
+        let hit = sweep_shape_vs_shape(
+            &circle,
+            &circle,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(5.0, 0.0),
+            0.0,
+            0.0,
+            Vec2::new(10.0, 0.0),
+        )
+        .unwrap();
+        assert!((hit.toi - 0.3).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+        // A lying capsule hit from above right over the center of its rounded end
+        let capsule = Shape::Capsule {
+            half_length: 2.0,
+            radius:      0.5,
+        };
+        let hit = sweep_shape_vs_shape(
+            &circle,
+            &capsule,
+            Vec2::new(2.0, 5.0),
+            Vec2::new(0.0, 0.0),
+            0.0,
+            std::f32::consts::FRAC_PI_2,
+            Vec2::new(0.0, -10.0),
+        )
+        .unwrap();
+        assert!((hit.toi - 0.35).abs() < 1e-5);
+        assert!((hit.normal - Vec2::new(0.0, 1.0)).length() < 1e-5);
     }
 }
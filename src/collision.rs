@@ -1,17 +1,128 @@
+use std::ops::Neg;
+
 use crate::Vec2;
 
-#[derive(Debug)]
-pub struct Contact<T> {
+/**
+ * Maximum number of points in a `ContactManifold`
+ */
+pub const MAX_MANIFOLD_POINTS: usize = 2;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContactPoint<T> {
+    /**
+     * World space point where the bodies touch
+     */
+    pub position:          Vec2<T>,
     pub penetration_depth: T,
-    pub normal:            Vec2<T>,
+    /**
+     * Identifies the pair of features (edges or vertices) that produced
+     * the point, stays the same between steps while they keep touching
+     */
+    pub feature:           u32,
+}
+
+/**
+ * All points where two shapes touch, sharing a single normal
+ * that points from the first towards the second shape
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct ContactManifold<T> {
+    pub normal:  Vec2<T>,
+    points:      [ContactPoint<T>; MAX_MANIFOLD_POINTS],
+    point_count: usize,
+}
+
+impl<T> ContactManifold<T>
+where
+    T: Copy,
+{
+    #[must_use]
+    pub fn new(normal: Vec2<T>, point: ContactPoint<T>) -> Self {
+        Self {
+            normal,
+            points: [point; MAX_MANIFOLD_POINTS],
+            point_count: 1,
+        }
+    }
+
+    /**
+     * Adds a point to the manifold,
+     * points beyond `MAX_MANIFOLD_POINTS` are ignored
+     */
+    pub fn push(&mut self, point: ContactPoint<T>) {
+        if self.point_count < MAX_MANIFOLD_POINTS {
+            self.points[self.point_count] = point;
+            self.point_count += 1;
+        }
+    }
+
+    #[must_use]
+    pub fn points(&self) -> &[ContactPoint<T>] {
+        &self.points[..self.point_count]
+    }
+}
+
+impl ContactManifold<f32> {
+    /**
+     * Depth of the deepest point in the manifold
+     */
+    #[must_use]
+    pub fn penetration_depth(&self) -> f32 {
+        self.points()
+            .iter()
+            .fold(0f32, |depth, point| depth.max(point.penetration_depth))
+    }
+}
+
+impl<T> ContactManifold<T>
+where
+    T: Neg<Output = T>,
+{
+    /**
+     * Flips the normal of the manifold,
+     * used when the shapes were checked in the opposite order
+     */
+    #[must_use]
+    pub fn inverted(self) -> Self {
+        Self {
+            normal: -self.normal,
+            ..self
+        }
+    }
+}
+
+/**
+ * Where a ray first hits a shape
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct RayIntersection<T> {
+    /**
+     * Time of impact, the distance along the ray
+     * in multiples of its direction
+     */
+    pub toi:    T,
+    /**
+     * Surface normal at the hit, pointing out of the shape
+     */
+    pub normal: Vec2<T>,
 }
 
 #[derive(Debug)]
 pub struct Collision<T, Handle>
 where
     Handle: Eq + std::hash::Hash + PartialEq, {
-    pub contact: Contact<T>,
-    pub pair:    CollisionPair<Handle>,
+    pub manifold:  ContactManifold<T>,
+    pub pair:      CollisionPair<Handle>,
+    /**
+     * Colliders of a and b that touch, 0 is the shape of a body
+     * and `i + 1` its collider `i`
+     */
+    pub colliders: (usize, usize),
+    /**
+     * One of the colliders is a sensor,
+     * the overlap is reported but never resolved
+     */
+    pub sensor:    bool,
 }
 
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
@@ -21,3 +132,124 @@ where
     pub a: Handle,
     pub b: Handle,
 }
+
+impl<Handle> CollisionPair<Handle>
+where
+    Handle: Eq + std::hash::Hash + PartialEq + Ord,
+{
+    /**
+     * The same pair with the lowest handle first,
+     * so that a pair is found regardless of the order it was reported in
+     */
+    #[must_use]
+    pub fn ordered(self) -> Self {
+        if self.b < self.a {
+            Self {
+                a: self.b,
+                b: self.a,
+            }
+        } else {
+            self
+        }
+    }
+}
+
+/**
+ * Change in the contact between two colliders of two bodies from one step
+ * to the next, `sensor` is set when one of the colliders is a sensor.
+ * `colliders` are the colliders of a and b as in `Collision::colliders`
+ */
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CollisionEvent<Handle>
+where
+    Handle: Eq + std::hash::Hash + PartialEq, {
+    /**
+     * The bodies started touching this step
+     */
+    Started {
+        pair:      CollisionPair<Handle>,
+        colliders: (usize, usize),
+        sensor:    bool,
+    },
+    /**
+     * The bodies were already touching in the previous step
+     */
+    Persisted {
+        pair:      CollisionPair<Handle>,
+        colliders: (usize, usize),
+        sensor:    bool,
+    },
+    /**
+     * The bodies stopped touching, or one of them was removed
+     */
+    Ended {
+        pair:      CollisionPair<Handle>,
+        colliders: (usize, usize),
+        sensor:    bool,
+    },
+}
+
+impl<Handle> CollisionEvent<Handle>
+where
+    Handle: Eq + std::hash::Hash + PartialEq + Copy,
+{
+    #[must_use]
+    pub fn pair(&self) -> CollisionPair<Handle> {
+        match self {
+            CollisionEvent::Started { pair, .. }
+            | CollisionEvent::Persisted { pair, .. }
+            | CollisionEvent::Ended { pair, .. } => *pair,
+        }
+    }
+
+    #[must_use]
+    pub fn colliders(&self) -> (usize, usize) {
+        match self {
+            CollisionEvent::Started { colliders, .. }
+            | CollisionEvent::Persisted { colliders, .. }
+            | CollisionEvent::Ended { colliders, .. } => *colliders,
+        }
+    }
+
+    #[must_use]
+    pub fn is_sensor(&self) -> bool {
+        match self {
+            CollisionEvent::Started { sensor, .. }
+            | CollisionEvent::Persisted { sensor, .. }
+            | CollisionEvent::Ended { sensor, .. } => *sensor,
+        }
+    }
+
+    /**
+     * The same event with the handles of the pair mapped to other handles,
+     * returns `None` if any of them can't be mapped
+     */
+    pub fn try_map<Other, F>(self, mut func: F) -> Option<CollisionEvent<Other>>
+    where
+        Other: Eq + std::hash::Hash + PartialEq,
+        F: FnMut(Handle) -> Option<Other>, {
+        let pair = self.pair();
+        let pair = CollisionPair {
+            a: func(pair.a)?,
+            b: func(pair.b)?,
+        };
+        let (colliders, sensor) = (self.colliders(), self.is_sensor());
+        Some(match self {
+            CollisionEvent::Started { .. } => CollisionEvent::Started {
+                pair,
+                colliders,
+                sensor,
+            },
+            CollisionEvent::Persisted { .. } => CollisionEvent::Persisted {
+                pair,
+                colliders,
+                sensor,
+            },
+            CollisionEvent::Ended { .. } => CollisionEvent::Ended {
+                pair,
+                colliders,
+                sensor,
+            },
+        })
+    }
+}
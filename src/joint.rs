@@ -0,0 +1,241 @@
+use crate::{world::ArenaHandle, Vec2};
+
+/**
+ * Drives a joint towards a relative speed,
+ * using no more than `max_force` (or torque for angular motors)
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motor {
+    pub speed:     f32,
+    pub max_force: f32,
+}
+
+/**
+ * How a joint constrains the anchors of its bodies
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JointKind {
+    /**
+     * Keeps the anchors at a fixed distance like a rigid rod,
+     * chained together they make up ropes
+     */
+    Distance { length: f32 },
+    /**
+     * Pulls the anchors towards a rest length like a damped spring,
+     * stiffness is in force per unit of stretch and damping in force per unit of speed.
+     * Without either it holds the rest length like a distance joint
+     */
+    Spring {
+        rest_length: f32,
+        stiffness:   f32,
+        damping:     f32,
+    },
+    /**
+     * Pins the anchors together, the bodies rotate freely around them like a hinge.
+     * Limits and the motor act on the angle of b relative to a
+     */
+    Revolute {
+        limits: Option<(f32, f32)>,
+        motor:  Option<Motor>,
+    },
+    /**
+     * Lets the anchors slide along an axis fixed to a without rotating, like a piston.
+     * Limits and the motor act on the translation of b along the axis
+     */
+    Prismatic {
+        axis:   Vec2<f32>,
+        limits: Option<(f32, f32)>,
+        motor:  Option<Motor>,
+    },
+    /**
+     * Glues the bodies together at the anchors
+     */
+    Weld,
+    /**
+     * Pulls the anchor of b towards a point in world space like a damped spring,
+     * using no more than `max_force`. Used to drag bodies around, a is the same body
+     */
+    Target {
+        target:    Vec2<f32>,
+        stiffness: f32,
+        damping:   f32,
+        max_force: f32,
+    },
+}
+
+/**
+ * A constraint between two bodies of a `PhysicsWorld`
+ */
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub a:                 ArenaHandle,
+    pub b:                 ArenaHandle,
+    /**
+     * Points the joint is attached to, relative to the bodies in their local space
+     */
+    pub a_anchor:          Vec2<f32>,
+    pub b_anchor:          Vec2<f32>,
+    pub kind:              JointKind,
+    /**
+     * Angle of b relative to a the joint holds or measures its limits from,
+     * used by revolute, prismatic and weld joints
+     */
+    pub reference_angle:   f32,
+    /**
+     * Whether the connected bodies still collide with each other
+     */
+    pub collide_connected: bool,
+}
+
+impl Joint {
+    #[must_use]
+    pub fn new(
+        a: ArenaHandle,
+        b: ArenaHandle,
+        a_anchor: Vec2<f32>,
+        b_anchor: Vec2<f32>,
+        kind: JointKind,
+    ) -> Self {
+        Self {
+            a,
+            b,
+            a_anchor,
+            b_anchor,
+            kind,
+            reference_angle: 0f32,
+            collide_connected: false,
+        }
+    }
+
+    #[must_use]
+    pub fn distance(
+        a: ArenaHandle,
+        b: ArenaHandle,
+        a_anchor: Vec2<f32>,
+        b_anchor: Vec2<f32>,
+        length: f32,
+    ) -> Self {
+        Self::new(a, b, a_anchor, b_anchor, JointKind::Distance {
+            length: length.max(0f32),
+        })
+    }
+
+    #[must_use]
+    pub fn spring(
+        a: ArenaHandle,
+        b: ArenaHandle,
+        a_anchor: Vec2<f32>,
+        b_anchor: Vec2<f32>,
+        rest_length: f32,
+        stiffness: f32,
+        damping: f32,
+    ) -> Self {
+        Self::new(a, b, a_anchor, b_anchor, JointKind::Spring {
+            rest_length: rest_length.max(0f32),
+            stiffness:   stiffness.max(0f32),
+            damping:     damping.max(0f32),
+        })
+    }
+
+    #[must_use]
+    pub fn revolute(
+        a: ArenaHandle,
+        b: ArenaHandle,
+        a_anchor: Vec2<f32>,
+        b_anchor: Vec2<f32>,
+    ) -> Self {
+        Self::new(a, b, a_anchor, b_anchor, JointKind::Revolute {
+            limits: None,
+            motor:  None,
+        })
+    }
+
+    /**
+     * Slider along an axis given in the local space of a
+     */
+    #[must_use]
+    pub fn prismatic(
+        a: ArenaHandle,
+        b: ArenaHandle,
+        a_anchor: Vec2<f32>,
+        b_anchor: Vec2<f32>,
+        axis: Vec2<f32>,
+    ) -> Self {
+        Self::new(a, b, a_anchor, b_anchor, JointKind::Prismatic {
+            axis:   axis.normalize(),
+            limits: None,
+            motor:  None,
+        })
+    }
+
+    #[must_use]
+    pub fn weld(a: ArenaHandle, b: ArenaHandle, a_anchor: Vec2<f32>, b_anchor: Vec2<f32>) -> Self {
+        Self::new(a, b, a_anchor, b_anchor, JointKind::Weld)
+    }
+
+    /**
+     * Drags a body by an anchor towards a target point
+     */
+    #[must_use]
+    pub fn target(
+        body: ArenaHandle,
+        anchor: Vec2<f32>,
+        target: Vec2<f32>,
+        stiffness: f32,
+        damping: f32,
+        max_force: f32,
+    ) -> Self {
+        Self::new(body, body, anchor, anchor, JointKind::Target {
+            target,
+            stiffness: stiffness.max(0f32),
+            damping: damping.max(0f32),
+            max_force: max_force.max(0f32),
+        })
+    }
+
+    /**
+     * Moves the target of a target joint, ignored by other joints
+     */
+    pub fn set_target(&mut self, point: Vec2<f32>) {
+        if let JointKind::Target { target, .. } = &mut self.kind {
+            *target = point;
+        }
+    }
+
+    #[must_use]
+    pub fn with_reference_angle(mut self, reference_angle: f32) -> Self {
+        self.reference_angle = reference_angle;
+        self
+    }
+
+    /**
+     * Limits the angle of revolute or the translation of prismatic joints,
+     * ignored by other joints
+     */
+    #[must_use]
+    pub fn with_limits(mut self, lower: f32, upper: f32) -> Self {
+        if let JointKind::Revolute { limits, .. } | JointKind::Prismatic { limits, .. } =
+            &mut self.kind
+        {
+            *limits = Some((lower.min(upper), lower.max(upper)));
+        }
+        self
+    }
+
+    /**
+     * Adds a motor to revolute or prismatic joints,
+     * ignored by other joints
+     */
+    #[must_use]
+    pub fn with_motor(mut self, speed: f32, max_force: f32) -> Self {
+        if let JointKind::Revolute { motor, .. } | JointKind::Prismatic { motor, .. } =
+            &mut self.kind
+        {
+            *motor = Some(Motor {
+                speed,
+                max_force: max_force.max(0f32),
+            });
+        }
+        self
+    }
+}
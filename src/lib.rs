@@ -4,6 +4,7 @@
 pub mod body;
 mod checks;
 mod collision;
+pub mod joint;
 mod quad_tree;
 pub mod shape;
 mod vec;
@@ -1,9 +1,11 @@
 use std::collections::HashSet;
 
 use crate::{
+    checks::ray_vs_aabb,
     collision::CollisionPair,
     shape::AABB,
     world::broad::{BroadPhase, BroadPhaseElement},
+    Vec2,
 };
 
 const MAX_DEPTH: u8 = 8;
@@ -21,10 +23,6 @@ impl<Handle: Clone> Node<Handle> {
     fn is_branch(&self) -> bool {
         matches!(self, Node::Branch(_))
     }
-
-    fn is_leaf(&self) -> bool {
-        matches!(self, Node::Leaf(_))
-    }
 }
 
 #[derive(Debug)]
@@ -117,7 +115,7 @@ where
     fn inner_check_collisions(&self, collision_set: &mut HashSet<CollisionPair<Handle>>) {
         match &self.node {
             Node::Branch(nodes) => {
-                for node in nodes.iter() {
+                for node in nodes {
                     node.inner_check_collisions(collision_set);
                 }
             },
@@ -175,7 +173,41 @@ impl<Handle: Clone + Copy + PartialEq + std::fmt::Debug + PartialEq + Eq + std::
                     children.swap_remove(index);
                 }
             },
-        };
+        }
+    }
+
+    fn move_element(&mut self, element: BroadPhaseElement<Handle>, aabb: AABB<i32>) {
+        let was_inside = element.aabb.intersects(&self.bounds);
+        let is_inside = aabb.intersects(&self.bounds);
+
+        if !was_inside && !is_inside {
+            return;
+        } else if !was_inside {
+            self.inner_insert(BroadPhaseElement {
+                aabb,
+                handle: element.handle,
+            });
+            return;
+        } else if !is_inside {
+            self.remove(element);
+            return;
+        }
+
+        match &mut self.node {
+            Node::Branch(nodes) => {
+                for node in nodes {
+                    node.move_element(element, aabb);
+                }
+            },
+            Node::Leaf(children) => {
+                if let Some(child) = children
+                    .iter_mut()
+                    .find(|child| element.handle == child.handle)
+                {
+                    child.aabb = aabb;
+                }
+            },
+        }
     }
 
     fn check(&self, aabb: AABB<i32>, collisions: &mut HashSet<Handle>) {
@@ -198,6 +230,32 @@ impl<Handle: Clone + Copy + PartialEq + std::fmt::Debug + PartialEq + Eq + std::
         }
     }
 
+    fn check_ray(
+        &self,
+        origin: Vec2<f32>,
+        direction: Vec2<f32>,
+        max_toi: f32,
+        hits: &mut HashSet<Handle>,
+    ) {
+        if ray_vs_aabb(origin, direction, max_toi, &self.bounds).is_none() {
+            return;
+        }
+        match &self.node {
+            Node::Branch(nodes) => {
+                for node in nodes {
+                    node.check_ray(origin, direction, max_toi, hits);
+                }
+            },
+            Node::Leaf(children) => {
+                for child in children {
+                    if ray_vs_aabb(origin, direction, max_toi, &child.aabb).is_some() {
+                        hits.insert(child.handle);
+                    }
+                }
+            },
+        }
+    }
+
     fn check_collisions(&self) -> Vec<CollisionPair<Handle>> {
         let mut collisions = HashSet::new();
 
@@ -270,6 +328,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::cast_precision_loss)]
     fn it_splits_into_quadrants() {
         let mut quad_tree = QuadTree::new(0, AABB::new(-10, -10, 20, 20));
         let mut bodies = Arena::new();
@@ -280,15 +339,11 @@ mod tests {
             let x = ((i / (length / 4)) % 2) as f32 * 20.0 - 10.0;
             let y = (i / (length / 2)) as f32 * 20.0 - 10.0;
 
-            let body = Body::new(
-                1.0,
-                1.0,
-                Shape::Circle(Circle::new(0.1)),
-                Vec2::new(x, y),
-                false,
-                false,
-                Body::default().entity,
-            );
+            let body = Body {
+                shape: Shape::Circle(Circle::new(0.1)),
+                position: Vec2::new(x, y),
+                ..Body::default()
+            };
 
             quad_tree.insert(BroadPhaseElement {
                 aabb:   body.get_aabb(),
@@ -306,6 +361,12 @@ mod tests {
     }
 
     #[test]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::float_cmp,
+        clippy::inconsistent_struct_constructor,
+        clippy::needless_for_each
+    )]
     fn it_removes_body() {
         let mut quad_tree = QuadTree::new(0, AABB::new(-10, -10, 20, 20));
         let mut bodies = Arena::new();
@@ -316,15 +377,11 @@ mod tests {
             let x = ((i / (length / 4)) % 2) as f32 * 20.0 - 10.0;
             let y = (i / (length / 2)) as f32 * 20.0 - 10.0;
 
-            let body = Body::new(
-                1.0,
-                1.0,
-                Shape::Circle(Circle::new(0.1)),
-                Vec2::new(x, y),
-                false,
-                false,
-                Body::default().entity,
-            );
+            let body = Body {
+                shape: Shape::Circle(Circle::new(0.1)),
+                position: Vec2::new(x, y),
+                ..Body::default()
+            };
             let aabb = body.get_aabb();
             let handle = bodies.insert(body);
             if x == -10.0 && y == -10.0 {
@@ -348,4 +405,44 @@ mod tests {
             panic!("Quad tree is a leaf");
         }
     }
+
+    #[test]
+    fn it_moves_body_between_quadrants() {
+        let mut quad_tree = QuadTree::new(0, AABB::new(-10, -10, 20, 20));
+        let mut bodies = Arena::new();
+
+        // Leaves the last quadrant empty
+        for (x, y) in [(-10.0, -10.0), (10.0, -10.0), (-10.0, 10.0)] {
+            for _ in 0..MAX_CHILDREN {
+                let body = Body {
+                    shape: Shape::Circle(Circle::new(0.1)),
+                    position: Vec2::new(x, y),
+                    ..Body::default()
+                };
+                quad_tree.insert(BroadPhaseElement {
+                    aabb:   body.get_aabb(),
+                    handle: bodies.insert(body),
+                });
+            }
+        }
+
+        let (handle, body) = bodies.iter_mut().next().unwrap();
+        let element = BroadPhaseElement {
+            aabb: body.get_aabb(),
+            handle,
+        };
+        body.position = Vec2::new(10.0, 10.0);
+        quad_tree.move_element(element, body.get_aabb());
+
+        let mut collisions = HashSet::new();
+        quad_tree.check(body.get_aabb(), &mut collisions);
+        assert!(collisions.contains(&handle));
+
+        if let Node::Branch(nodes) = quad_tree.node {
+            assert!(is_leaf_with_len(&nodes[0].node, MAX_CHILDREN - 1));
+            assert!(is_leaf_with_len(&nodes[3].node, 1));
+        } else {
+            panic!("Quad tree is a leaf");
+        }
+    }
 }
@@ -1,8 +1,10 @@
 pub mod aabb;
 mod circle;
+mod polygon;
 
 pub use aabb::AABB;
 pub use circle::Circle;
+pub use polygon::Polygon;
 
 use crate::Vec2;
 
@@ -10,11 +12,149 @@ use crate::Vec2;
 pub enum Shape {
     Circle(Circle),
     Rect(Vec2<f32>),
+    Polygon(Polygon),
+    /**
+     * Rounded line along the local y axis,
+     * reaching `half_length` from the body position in both directions
+     */
+    Capsule {
+        half_length: f32,
+        radius:      f32,
+    },
+    /**
+     * Infinitely thin line between two points relative to the body position
+     */
+    Segment {
+        a: Vec2<f32>,
+        b: Vec2<f32>,
+    },
+}
+
+/**
+ * Mass properties of a shape or body with a uniform density
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassProperties {
+    pub area:           f32,
+    pub mass:           f32,
+    /**
+     * Center of mass relative to the body position, in its local space.
+     * Bodies still rotate around their position, not around this point
+     */
+    pub center_of_mass: Vec2<f32>,
+    /**
+     * Moment of inertia around the body position,
+     * which is the point bodies rotate around
+     */
+    pub inertia:        f32,
 }
 
 impl Shape {
+    /**
+     * Area of the shape, segments have none
+     */
+    #[must_use]
+    pub fn area(&self) -> f32 {
+        match self {
+            Shape::Circle(circle) => std::f32::consts::PI * circle.radius * circle.radius,
+            Shape::Rect(rect) => rect.x * rect.y,
+            Shape::Polygon(polygon) => polygon.area(),
+            Shape::Capsule {
+                half_length,
+                radius,
+            } => 4f32 * radius * half_length + std::f32::consts::PI * radius * radius,
+            Shape::Segment { .. } => 0f32,
+        }
+    }
+
+    /**
+     * Center of the area of the shape relative to the body position
+     */
+    #[must_use]
+    pub fn center_of_mass(&self) -> Vec2<f32> {
+        match self {
+            Shape::Polygon(polygon) => polygon.centroid(),
+            Shape::Segment { a, b } => (*a + *b) / 2f32,
+            Shape::Circle(_) | Shape::Rect(_) | Shape::Capsule { .. } => Vec2::new(0f32, 0f32),
+        }
+    }
+
+    /**
+     * Area, mass, center of mass and inertia of the shape
+     * for a density in mass per unit of area
+     */
+    #[must_use]
+    pub fn mass_properties(&self, density: f32) -> MassProperties {
+        let area = self.area();
+        let mass = area * density;
+        MassProperties {
+            area,
+            mass,
+            center_of_mass: self.center_of_mass(),
+            inertia: self.moment_of_inertia(mass),
+        }
+    }
+
+    /**
+     * Moment of inertia around the body position for a given mass
+     */
+    #[must_use]
+    pub fn moment_of_inertia(&self, mass: f32) -> f32 {
+        match self {
+            Shape::Circle(circle) => mass * circle.radius * circle.radius / 2f32,
+            Shape::Rect(rect) => mass * (rect.x * rect.x + rect.y * rect.y) / 12f32,
+            Shape::Polygon(polygon) => {
+                let (numerator, denominator) = polygon
+                    .vertices
+                    .iter()
+                    .zip(polygon.vertices.iter().cycle().skip(1))
+                    .fold((0f32, 0f32), |(numerator, denominator), (a, b)| {
+                        let cross = a.cross(b);
+                        (
+                            numerator + cross * (a.dot(a) + a.dot(b) + b.dot(b)),
+                            denominator + cross,
+                        )
+                    });
+                mass * numerator / (6f32 * denominator)
+            },
+            Shape::Capsule {
+                half_length,
+                radius,
+            } => {
+                // A rect between the two end points and two half circles,
+                // with the mass divided between them by area
+                let rect_area = 4f32 * radius * half_length;
+                let circle_area = std::f32::consts::PI * radius * radius;
+                let rect_mass = mass * rect_area / (rect_area + circle_area);
+                let circle_mass = mass - rect_mass;
+
+                let rect_inertia =
+                    rect_mass * (4f32 * radius * radius + 4f32 * half_length * half_length) / 12f32;
+                // Each half circle is moved out from the center by the half length,
+                // its center of mass lies 4r / 3pi beyond that
+                let centroid = 4f32 * radius / (3f32 * std::f32::consts::PI);
+                let circle_inertia = circle_mass
+                    * (radius * radius / 2f32
+                        + half_length * half_length
+                        + 2f32 * half_length * centroid);
+
+                rect_inertia + circle_inertia
+            },
+            Shape::Segment { a, b } => {
+                let center = (*a + *b) / 2f32;
+                mass * ((*b - *a).length_squared() / 12f32 + center.length_squared())
+            },
+        }
+    }
+
+    /**
+     * Integer bounding box enclosing the shape
+     * at a position and rotated by an angle
+     */
     #[must_use]
-    pub fn get_aabb(&self, position: Vec2<f32>) -> AABB<i32> {
+    // Positions are far inside the range of the broad phase integers
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn get_aabb(&self, position: Vec2<f32>, angle: f32) -> AABB<i32> {
         match self {
             Shape::Circle(circle) => {
                 let i_radius = circle.radius.ceil() as i32;
@@ -26,12 +166,98 @@ impl Shape {
                     i_radius * 2,
                 )
             },
-            Shape::Rect(rect) => AABB::new(
-                position.x.floor() as i32,
-                position.y.floor() as i32,
-                rect.x.ceil() as i32,
-                rect.y.ceil() as i32,
-            ),
+            Shape::Rect(rect) => {
+                let (sin, cos) = angle.sin_cos();
+                let (sin, cos) = (sin.abs(), cos.abs());
+                let half = *rect / 2f32;
+                let extents = Vec2::new(half.x * cos + half.y * sin, half.x * sin + half.y * cos);
+                enclosing_aabb(position - extents, position + extents)
+            },
+            Shape::Polygon(polygon) => {
+                let (min, max) = rotated_bounds(&polygon.vertices, angle);
+                enclosing_aabb(min + position, max + position)
+            },
+            Shape::Capsule {
+                half_length,
+                radius,
+            } => {
+                let (min, max) = rotated_bounds(
+                    &[Vec2::new(0f32, -half_length), Vec2::new(0f32, *half_length)],
+                    angle,
+                );
+                let radius = Vec2::new(*radius, *radius);
+                enclosing_aabb(min + position - radius, max + position + radius)
+            },
+            Shape::Segment { a, b } => {
+                let (min, max) = rotated_bounds(&[*a, *b], angle);
+                enclosing_aabb(min + position, max + position)
+            },
         }
     }
 }
+
+/**
+ * Minimum and maximum corners of a list of points rotated by an angle
+ */
+fn rotated_bounds(points: &[Vec2<f32>], angle: f32) -> (Vec2<f32>, Vec2<f32>) {
+    points.iter().fold(
+        (
+            Vec2::new(f32::INFINITY, f32::INFINITY),
+            Vec2::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        ),
+        |(min, max), point| {
+            let point = point.rotate(angle);
+            (
+                Vec2::new(min.x.min(point.x), min.y.min(point.y)),
+                Vec2::new(max.x.max(point.x), max.y.max(point.y)),
+            )
+        },
+    )
+}
+
+/**
+ * Smallest integer bounding box containing the given float bounds
+ */
+// Bounds are far inside the range of the broad phase integers
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn enclosing_aabb(min: Vec2<f32>, max: Vec2<f32>) -> AABB<i32> {
+    AABB {
+        min: Vec2::new(min.x.floor() as i32, min.y.floor() as i32),
+        max: Vec2::new(max.x.ceil() as i32, max.y.ceil() as i32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_computes_mass_properties() {
+        let rect = Shape::Rect(Vec2::new(2.0, 4.0)).mass_properties(0.5);
+        assert!((rect.area - 8.0).abs() < 1e-5);
+        assert!((rect.mass - 4.0).abs() < 1e-5);
+        assert!((rect.inertia - 4.0 * 20.0 / 12.0).abs() < 1e-5);
+
+        let circle = Shape::Circle(Circle::new(1.0)).mass_properties(2.0);
+        assert!((circle.mass - 2.0 * std::f32::consts::PI).abs() < 1e-5);
+
+        // A square away from the body position rotates around it
+        let square = Shape::Polygon(Polygon::new(vec![
+            Vec2::new(1.0, 1.0),
+            Vec2::new(3.0, 1.0),
+            Vec2::new(3.0, 3.0),
+            Vec2::new(1.0, 3.0),
+        ]));
+        let square = square.mass_properties(1.0);
+        assert!((square.center_of_mass - Vec2::new(2.0, 2.0)).length() < 1e-5);
+        assert!((square.area - 4.0).abs() < 1e-5);
+        assert!((square.inertia - (4.0 * 4.0 / 6.0 + 4.0 * 8.0)).abs() < 1e-4);
+
+        let segment = Shape::Segment {
+            a: Vec2::new(0.0, 0.0),
+            b: Vec2::new(2.0, 0.0),
+        };
+        assert_eq!(segment.center_of_mass(), Vec2::new(1.0, 0.0));
+        assert!(segment.mass_properties(1.0).mass.abs() < 1e-5);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::Vec2;
+
+/**
+ * A convex polygon with its vertices relative to the body position.
+ * Vertices are stored in counter-clockwise order together with
+ * the outward facing normal of the edge starting at each vertex.
+ */
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    pub vertices: Vec<Vec2<f32>>,
+    pub normals:  Vec<Vec2<f32>>,
+}
+
+impl Polygon {
+    /**
+     * Creates a polygon from a list of convex vertices,
+     * the winding order of the vertices does not matter.
+     *
+     * # Panics
+     * Panics if fewer than three vertices are given
+     */
+    #[must_use]
+    pub fn new(mut vertices: Vec<Vec2<f32>>) -> Self {
+        assert!(
+            vertices.len() >= 3,
+            "A polygon needs at least three vertices"
+        );
+
+        if signed_area(&vertices) < 0f32 {
+            vertices.reverse();
+        }
+
+        let normals = vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .map(|(a, b)| {
+                let edge = *b - *a;
+                Vec2::new(edge.y, -edge.x).normalize()
+            })
+            .collect();
+
+        Self { vertices, normals }
+    }
+
+    #[must_use]
+    pub fn area(&self) -> f32 {
+        signed_area(&self.vertices)
+    }
+
+    /**
+     * Center of the area of the polygon relative to the body position
+     */
+    #[must_use]
+    pub(crate) fn centroid(&self) -> Vec2<f32> {
+        let area = self.area();
+        if area == 0f32 {
+            return Vec2::new(0f32, 0f32);
+        }
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .fold(Vec2::new(0f32, 0f32), |centroid, (a, b)| {
+                centroid + (*a + *b) * a.cross(b)
+            })
+            / (6f32 * area)
+    }
+}
+
+fn signed_area(vertices: &[Vec2<f32>]) -> f32 {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .fold(0f32, |area, (a, b)| area + a.cross(b))
+        / 2f32
+}
@@ -1,4 +1,4 @@
-use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Copy)]
 pub struct Vec2<T> {
@@ -44,6 +44,60 @@ impl Vec2<f32> {
             y: self.y.abs(),
         }
     }
+
+    /**
+     * Z component of the 3D cross product of two vectors
+     */
+    #[inline]
+    #[must_use]
+    pub fn cross(&self, other: &Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    /**
+     * The vector rotated 90 degrees counter-clockwise,
+     * scaling it by `w` gives the cross product of a scalar `w` and the vector
+     */
+    #[inline]
+    #[must_use]
+    pub fn perpendicular(&self) -> Self {
+        Self::new(-self.y, self.x)
+    }
+
+    /**
+     * The vector rotated counter-clockwise by an angle in radians
+     */
+    #[inline]
+    #[must_use]
+    pub fn rotate(&self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length_squared(&self) -> f32 {
+        self.dot(self)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    /**
+     * Returns the vector scaled to unit length,
+     * or a zero vector if the length is zero
+     */
+    #[must_use]
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        if length == 0f32 {
+            return Self::new(0f32, 0f32);
+        }
+        *self / length
+    }
 }
 
 impl Vec2<i32> {
@@ -146,6 +200,20 @@ where
     }
 }
 
+impl<T> Neg for Vec2<T>
+where
+    T: Neg<Output = T>,
+{
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
 impl<T> Mul for Vec2<T>
 where
     T: Mul<Output = T>,
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::{collision::CollisionPair, shape::AABB};
+use crate::{collision::CollisionPair, shape::AABB, Vec2};
 
 #[derive(Debug, Clone, Copy)]
 pub struct BroadPhaseElement<Handle> {
@@ -30,7 +30,23 @@ where
     Handle: Clone + Eq + PartialEq + std::hash::Hash, {
     fn insert(&mut self, element: BroadPhaseElement<Handle>);
     fn remove(&mut self, element: BroadPhaseElement<Handle>);
+    /**
+     * Moves an element from its current bounding box to a new one,
+     * only touching the parts of the broad phase either of them overlaps
+     */
+    fn move_element(&mut self, element: BroadPhaseElement<Handle>, aabb: AABB<i32>);
     fn check(&self, element: AABB<i32>, collisions: &mut HashSet<Handle>);
+    /**
+     * Finds the elements whose bounding box is hit by a ray
+     * within `max_toi` multiples of its direction
+     */
+    fn check_ray(
+        &self,
+        origin: Vec2<f32>,
+        direction: Vec2<f32>,
+        max_toi: f32,
+        hits: &mut HashSet<Handle>,
+    );
     fn check_collisions(&self) -> Vec<CollisionPair<Handle>>;
     fn clear(&mut self);
     fn len(&self) -> usize;
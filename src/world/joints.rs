@@ -0,0 +1,725 @@
+use std::collections::{HashMap, HashSet};
+
+use generational_arena::Arena;
+
+use super::{
+    broad::BroadPhase,
+    solver::{apply_impulse, inverse_mass, relative_velocity},
+    ArenaHandle,
+    IntegrationParameters,
+    JointHandle,
+    PhysicsWorld,
+};
+use crate::{
+    body::Body,
+    collision::CollisionPair,
+    joint::{Joint, JointKind, Motor},
+    Vec2,
+};
+
+/**
+ * Impulses accumulated on a joint during a step,
+ * used to warm start the solver in the next one
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct JointImpulse {
+    /**
+     * Impulse on the anchors of revolute, weld and target joints. Distance joints
+     * and springs only use x along the joint, prismatic joints across their axis
+     */
+    linear:  Vec2<f32>,
+    angular: f32,
+    motor:   f32,
+    lower:   f32,
+    upper:   f32,
+}
+
+impl Default for JointImpulse {
+    fn default() -> Self {
+        Self {
+            linear:  Vec2::new(0f32, 0f32),
+            angular: 0f32,
+            motor:   0f32,
+            lower:   0f32,
+            upper:   0f32,
+        }
+    }
+}
+
+pub type JointCache = HashMap<JointHandle, JointImpulse>;
+
+/**
+ * A single constraint row, the impulse moves the bodies along `linear`
+ * and turns them by their angular parts
+ */
+#[derive(Debug, Clone, Copy)]
+struct Row {
+    linear:    Vec2<f32>,
+    a_angular: f32,
+    b_angular: f32,
+}
+
+impl Row {
+    fn angular() -> Self {
+        Self {
+            linear:    Vec2::new(0f32, 0f32),
+            a_angular: 1f32,
+            b_angular: 1f32,
+        }
+    }
+
+    /**
+     * Row along a direction through the anchors at the given offsets
+     */
+    fn along(direction: Vec2<f32>, a_offset: Vec2<f32>, b_offset: Vec2<f32>) -> Self {
+        Self {
+            linear:    direction,
+            a_angular: a_offset.cross(&direction),
+            b_angular: b_offset.cross(&direction),
+        }
+    }
+
+    fn velocity(&self, a: &Body, b: &Body) -> f32 {
+        self.linear.dot(&(b.velocity - a.velocity)) + self.b_angular * b.angular_velocity
+            - self.a_angular * a.angular_velocity
+    }
+
+    fn inv_mass(&self, a: &Body, b: &Body) -> f32 {
+        let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+        let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+        let linear = self.linear.length_squared();
+        (a_inv_mass + b_inv_mass) * linear
+            + self.a_angular * self.a_angular * a_inv_inertia
+            + self.b_angular * self.b_angular * b_inv_inertia
+    }
+
+    fn apply(&self, a: &mut Body, b: &mut Body, impulse: f32) {
+        let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+        let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+        a.velocity -= self.linear * (impulse * a_inv_mass);
+        a.angular_velocity -= self.a_angular * impulse * a_inv_inertia;
+        b.velocity += self.linear * (impulse * b_inv_mass);
+        b.angular_velocity += self.b_angular * impulse * b_inv_inertia;
+    }
+
+    /**
+     * Moves the bodies by a part of the error of the row
+     */
+    fn correct(&self, a: &mut Body, b: &mut Body, error: f32, parameters: &IntegrationParameters) {
+        let inv_mass = self.inv_mass(a, b);
+        if inv_mass == 0f32 || error == 0f32 {
+            return;
+        }
+        let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+        let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+        let impulse = -error * parameters.position_correction() / inv_mass;
+        a.position -= self.linear * (impulse * a_inv_mass);
+        a.angle -= self.a_angular * impulse * a_inv_inertia;
+        b.position += self.linear * (impulse * b_inv_mass);
+        b.angle += self.b_angular * impulse * b_inv_inertia;
+    }
+}
+
+/**
+ * Solves `K * impulse = rhs` for a symmetric 2x2 mass matrix K
+ */
+fn solve_matrix(k11: f32, k12: f32, k22: f32, rhs: Vec2<f32>) -> Vec2<f32> {
+    let determinant = k11 * k22 - k12 * k12;
+    if determinant == 0f32 {
+        return Vec2::new(0f32, 0f32);
+    }
+    Vec2::new(
+        (k22 * rhs.x - k12 * rhs.y) / determinant,
+        (k11 * rhs.y - k12 * rhs.x) / determinant,
+    )
+}
+
+/**
+ * Impulse on the anchors of two bodies for the given change in their relative velocity
+ */
+fn solve_point(
+    a: &Body,
+    b: &Body,
+    a_offset: Vec2<f32>,
+    b_offset: Vec2<f32>,
+    rhs: Vec2<f32>,
+) -> Vec2<f32> {
+    let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+    let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+    let inv_mass = a_inv_mass + b_inv_mass;
+    let k11 = inv_mass
+        + a_offset.y * a_offset.y * a_inv_inertia
+        + b_offset.y * b_offset.y * b_inv_inertia;
+    let k12 = -a_offset.y * a_offset.x * a_inv_inertia - b_offset.y * b_offset.x * b_inv_inertia;
+    let k22 = inv_mass
+        + a_offset.x * a_offset.x * a_inv_inertia
+        + b_offset.x * b_offset.x * b_inv_inertia;
+    solve_matrix(k11, k12, k22, rhs)
+}
+
+/**
+ * Applies an impulse to a single body at an offset from its position
+ */
+fn apply_body_impulse(body: &mut Body, offset: Vec2<f32>, impulse: Vec2<f32>) {
+    let (inv_mass, inv_inertia) = inverse_mass(body);
+    body.velocity += impulse * inv_mass;
+    body.angular_velocity += offset.cross(&impulse) * inv_inertia;
+}
+
+/**
+ * Anchors of a joint relative to the bodies in world space
+ * and the vector from the anchor of a to the anchor of b
+ */
+fn anchors(a: &Body, b: &Body, joint: &Joint) -> (Vec2<f32>, Vec2<f32>, Vec2<f32>) {
+    let a_offset = joint.a_anchor.rotate(a.angle);
+    let b_offset = joint.b_anchor.rotate(b.angle);
+    (
+        a_offset,
+        b_offset,
+        (b.position + b_offset) - (a.position + a_offset),
+    )
+}
+
+/**
+ * Rows along and across the axis of a prismatic joint,
+ * together with the translation of b along the axis
+ */
+fn prismatic_rows(a: &Body, b: &Body, joint: &Joint, axis: Vec2<f32>) -> (Row, Row, f32) {
+    let (a_offset, b_offset, delta) = anchors(a, b, joint);
+    let axis = axis.rotate(a.angle);
+    let perpendicular = axis.perpendicular();
+    // The axis turns with a, which moves b across it when a rotates
+    (
+        Row::along(axis, a_offset + delta, b_offset),
+        Row::along(perpendicular, a_offset + delta, b_offset),
+        delta.dot(&axis),
+    )
+}
+
+/**
+ * Direction from the anchor of a towards the anchor of b and their distance
+ */
+fn direction(delta: Vec2<f32>) -> (Vec2<f32>, f32) {
+    let length = delta.length();
+    if length > f32::EPSILON {
+        (delta / length, length)
+    } else {
+        (Vec2::new(1f32, 0f32), length)
+    }
+}
+
+/**
+ * Error of a position outside of its limits, 0 within them
+ */
+fn limit_error(position: f32, (lower, upper): (f32, f32)) -> f32 {
+    if position < lower {
+        position - lower
+    } else if position > upper {
+        position - upper
+    } else {
+        0f32
+    }
+}
+
+/**
+ * A joint prepared for the sequential impulse solver
+ */
+#[derive(Debug)]
+pub struct JointConstraint {
+    pub handle:  JointHandle,
+    joint:       Joint,
+    a_offset:    Vec2<f32>,
+    b_offset:    Vec2<f32>,
+    /**
+     * Row along distance joints, springs and the axis of prismatic joints
+     */
+    axis:        Row,
+    /**
+     * Row across the axis of prismatic joints
+     */
+    across:      Row,
+    /**
+     * Length of distance joints and springs, angle of revolute
+     * and translation of prismatic joints
+     */
+    position:    f32,
+    /**
+     * Softness and bias of springs, both 0 for rigid joints
+     */
+    gamma:       f32,
+    bias:        f32,
+    /**
+     * Distance of target joints from their target, scaled by their softness
+     */
+    target_bias: Vec2<f32>,
+    dt:          f32,
+    impulse:     JointImpulse,
+}
+
+impl JointConstraint {
+    /**
+     * Prepares a joint for solving, returns `None` if one of its bodies is gone,
+     * neither is dynamic or it connects a body to itself
+     */
+    pub fn new(
+        bodies: &Arena<Body>,
+        handle: JointHandle,
+        joint: &Joint,
+        cache: &JointCache,
+        dt: f32,
+    ) -> Option<Self> {
+        let a = bodies.get(joint.a)?;
+        let b = bodies.get(joint.b)?;
+        if !a.body_type.is_dynamic() && !b.body_type.is_dynamic() {
+            return None;
+        }
+        // Only target joints act on a single body
+        if joint.a == joint.b && !matches!(joint.kind, JointKind::Target { .. }) {
+            return None;
+        }
+
+        let (a_offset, b_offset, delta) = anchors(a, b, joint);
+        let angle = b.angle - a.angle - joint.reference_angle;
+        let (axis, across, position) = match joint.kind {
+            JointKind::Distance { .. } | JointKind::Spring { .. } => {
+                let (direction, length) = direction(delta);
+                (
+                    Row::along(direction, a_offset, b_offset),
+                    Row::angular(),
+                    length,
+                )
+            },
+            JointKind::Prismatic { axis, .. } => prismatic_rows(a, b, joint, axis),
+            JointKind::Revolute { .. } | JointKind::Weld | JointKind::Target { .. } => {
+                (Row::angular(), Row::angular(), angle)
+            },
+        };
+
+        // Springs are solved as soft constraints, stable for any stiffness.
+        // Without stiffness, damping or a step to spread them over they are held rigidly
+        let softness = |stiffness: f32, damping: f32| {
+            let denominator = dt * (damping + dt * stiffness);
+            if denominator > 0f32 {
+                1f32 / denominator
+            } else {
+                0f32
+            }
+        };
+        let (gamma, bias) = match joint.kind {
+            JointKind::Spring {
+                rest_length,
+                stiffness,
+                damping,
+            } => {
+                let gamma = softness(stiffness, damping);
+                (gamma, (position - rest_length) * dt * stiffness * gamma)
+            },
+            JointKind::Target {
+                stiffness, damping, ..
+            } => (softness(stiffness, damping), 0f32),
+            _ => (0f32, 0f32),
+        };
+        let target_bias = match joint.kind {
+            JointKind::Target {
+                target, stiffness, ..
+            } => (b.position + b_offset - target) * (dt * stiffness * gamma),
+            _ => Vec2::new(0f32, 0f32),
+        };
+
+        Some(Self {
+            handle,
+            joint: joint.clone(),
+            a_offset,
+            b_offset,
+            axis,
+            across,
+            position,
+            gamma,
+            bias,
+            target_bias,
+            dt,
+            impulse: cache.get(&handle).copied().unwrap_or_default(),
+        })
+    }
+
+    /**
+     * Applies the impulses cached from the previous step
+     */
+    pub fn warm_start(&self, bodies: &mut Arena<Body>, parameters: &IntegrationParameters) {
+        if let JointKind::Target { .. } = self.joint.kind {
+            let body = bodies.get_mut(self.joint.b).unwrap();
+            body.angular_velocity -=
+                body.angular_velocity * parameters.target_angular_damping() * self.dt;
+            apply_body_impulse(body, self.b_offset, self.impulse.linear);
+            return;
+        }
+        let (a, b) = bodies.get2_mut(self.joint.a, self.joint.b);
+        let (a, b) = (a.unwrap(), b.unwrap());
+        let impulse = &self.impulse;
+        let axial = impulse.motor + impulse.lower - impulse.upper;
+
+        match self.joint.kind {
+            JointKind::Distance { .. } | JointKind::Spring { .. } => {
+                self.axis.apply(a, b, impulse.linear.x);
+            },
+            JointKind::Revolute { .. } => {
+                apply_impulse(a, b, self.a_offset, self.b_offset, impulse.linear);
+                Row::angular().apply(a, b, axial);
+            },
+            JointKind::Prismatic { .. } => {
+                self.across.apply(a, b, impulse.linear.x);
+                Row::angular().apply(a, b, impulse.angular);
+                self.axis.apply(a, b, axial);
+            },
+            JointKind::Weld => {
+                apply_impulse(a, b, self.a_offset, self.b_offset, impulse.linear);
+                Row::angular().apply(a, b, impulse.angular);
+            },
+            // Started on its single body above
+            JointKind::Target { .. } => {},
+        }
+    }
+
+    pub fn solve_velocity(&mut self, bodies: &mut Arena<Body>) {
+        if let JointKind::Target { max_force, .. } = self.joint.kind {
+            self.solve_target(bodies.get_mut(self.joint.b).unwrap(), max_force);
+            return;
+        }
+        let (a, b) = bodies.get2_mut(self.joint.a, self.joint.b);
+        let (a, b) = (a.unwrap(), b.unwrap());
+
+        match self.joint.kind {
+            JointKind::Distance { .. } | JointKind::Spring { .. } => {
+                let inv_mass = self.axis.inv_mass(a, b) + self.gamma;
+                if inv_mass == 0f32 {
+                    return;
+                }
+                let impulse =
+                    -(self.axis.velocity(a, b) + self.bias + self.gamma * self.impulse.linear.x)
+                        / inv_mass;
+                self.impulse.linear.x += impulse;
+                self.axis.apply(a, b, impulse);
+            },
+            JointKind::Revolute { limits, motor } => {
+                // Motors and limits first, the pin is more important
+                self.solve_motor(a, b, Row::angular(), motor);
+                self.solve_limits(a, b, Row::angular(), limits);
+                self.solve_anchors(a, b);
+            },
+            JointKind::Prismatic { limits, motor, .. } => {
+                self.solve_motor(a, b, self.axis, motor);
+                self.solve_limits(a, b, self.axis, limits);
+                self.impulse.linear.x += solve_row(a, b, self.across);
+                self.impulse.angular += solve_row(a, b, Row::angular());
+            },
+            JointKind::Weld => {
+                self.impulse.angular += solve_row(a, b, Row::angular());
+                self.solve_anchors(a, b);
+            },
+            // Solved on its single body above
+            JointKind::Target { .. } => {},
+        }
+    }
+
+    /**
+     * Pulls the anchor towards the target as a soft constraint,
+     * the accumulated impulse is clamped to the maximum force of the joint
+     */
+    fn solve_target(&mut self, body: &mut Body, max_force: f32) {
+        let (inv_mass, inv_inertia) = inverse_mass(body);
+        let offset = self.b_offset;
+        let velocity = body.velocity + offset.perpendicular() * body.angular_velocity;
+        let impulse = solve_matrix(
+            inv_mass + offset.y * offset.y * inv_inertia + self.gamma,
+            -offset.x * offset.y * inv_inertia,
+            inv_mass + offset.x * offset.x * inv_inertia + self.gamma,
+            -(velocity + self.target_bias + self.impulse.linear * self.gamma),
+        );
+
+        let previous = self.impulse.linear;
+        let max_impulse = max_force * self.dt;
+        let mut total = previous + impulse;
+        if total.length() > max_impulse {
+            total = total.normalize() * max_impulse;
+        }
+        self.impulse.linear = total;
+        apply_body_impulse(body, offset, total - previous);
+    }
+
+    /**
+     * Velocity that closes the distance to a limit within the step,
+     * none for a step without length
+     */
+    fn limit_bias(&self, distance: f32) -> f32 {
+        if self.dt > 0f32 {
+            distance.max(0f32) / self.dt
+        } else {
+            0f32
+        }
+    }
+
+    fn is_spring(&self) -> bool {
+        matches!(self.joint.kind, JointKind::Spring { .. })
+    }
+
+    /**
+     * Keeps the anchors of revolute and weld joints moving together
+     */
+    fn solve_anchors(&mut self, a: &mut Body, b: &mut Body) {
+        let velocity = relative_velocity(a, b, self.a_offset, self.b_offset);
+        let impulse = solve_point(a, b, self.a_offset, self.b_offset, -velocity);
+        self.impulse.linear += impulse;
+        apply_impulse(a, b, self.a_offset, self.b_offset, impulse);
+    }
+
+    /**
+     * Drives a row towards the speed of the motor,
+     * the accumulated impulse is clamped to what the motor can apply in a step
+     */
+    fn solve_motor(&mut self, a: &mut Body, b: &mut Body, row: Row, motor: Option<Motor>) {
+        let Some(motor) = motor else {
+            return;
+        };
+        let inv_mass = row.inv_mass(a, b);
+        if inv_mass == 0f32 {
+            return;
+        }
+        let max_impulse = motor.max_force * self.dt;
+        let impulse = -(row.velocity(a, b) - motor.speed) / inv_mass;
+        let total = (self.impulse.motor + impulse).clamp(-max_impulse, max_impulse);
+        let change = total - self.impulse.motor;
+        self.impulse.motor = total;
+        row.apply(a, b, change);
+    }
+
+    /**
+     * Stops a row at its limits, bodies are allowed to close in on
+     * a limit within a step but not to pass it
+     */
+    fn solve_limits(&mut self, a: &mut Body, b: &mut Body, row: Row, limits: Option<(f32, f32)>) {
+        let Some((lower, upper)) = limits else {
+            return;
+        };
+        let inv_mass = row.inv_mass(a, b);
+        if inv_mass == 0f32 {
+            return;
+        }
+
+        let bias = self.limit_bias(self.position - lower);
+        let impulse = -(row.velocity(a, b) + bias) / inv_mass;
+        let total = (self.impulse.lower + impulse).max(0f32);
+        let change = total - self.impulse.lower;
+        self.impulse.lower = total;
+        row.apply(a, b, change);
+
+        let bias = self.limit_bias(upper - self.position);
+        let impulse = -(-row.velocity(a, b) + bias) / inv_mass;
+        let total = (self.impulse.upper + impulse).max(0f32);
+        let change = total - self.impulse.upper;
+        self.impulse.upper = total;
+        row.apply(a, b, -change);
+    }
+
+    /**
+     * Runs a single position iteration, rigid joints move their bodies
+     * back by a part of the error while springs and targets are left to stretch
+     */
+    pub fn solve_position(&self, bodies: &mut Arena<Body>, parameters: &IntegrationParameters) {
+        let soft_spring = self.is_spring() && self.gamma > 0f32;
+        if soft_spring || matches!(self.joint.kind, JointKind::Target { .. }) {
+            return;
+        }
+        let (a, b) = bodies.get2_mut(self.joint.a, self.joint.b);
+        let (a, b) = (a.unwrap(), b.unwrap());
+        let angle = |a: &Body, b: &Body| b.angle - a.angle - self.joint.reference_angle;
+
+        match self.joint.kind {
+            JointKind::Distance { length }
+            | JointKind::Spring {
+                rest_length: length,
+                ..
+            } => {
+                let (a_offset, b_offset, delta) = anchors(a, b, &self.joint);
+                let (direction, current_length) = direction(delta);
+                Row::along(direction, a_offset, b_offset).correct(
+                    a,
+                    b,
+                    current_length - length,
+                    parameters,
+                );
+            },
+            JointKind::Target { .. } => {},
+            JointKind::Revolute { limits, .. } => {
+                if let Some(limits) = limits {
+                    let error = limit_error(angle(a, b), limits);
+                    Row::angular().correct(a, b, error, parameters);
+                }
+                self.correct_anchors(a, b, parameters);
+            },
+            JointKind::Prismatic { axis, limits, .. } => {
+                Row::angular().correct(a, b, angle(a, b), parameters);
+                let (along, across, translation) = prismatic_rows(a, b, &self.joint, axis);
+                if let Some(limits) = limits {
+                    along.correct(a, b, limit_error(translation, limits), parameters);
+                }
+                let (_, _, delta) = anchors(a, b, &self.joint);
+                across.correct(a, b, delta.dot(&across.linear), parameters);
+            },
+            JointKind::Weld => {
+                Row::angular().correct(a, b, angle(a, b), parameters);
+                self.correct_anchors(a, b, parameters);
+            },
+        }
+    }
+
+    /**
+     * Moves the anchors of revolute and weld joints back together
+     */
+    fn correct_anchors(&self, a: &mut Body, b: &mut Body, parameters: &IntegrationParameters) {
+        let (a_offset, b_offset, delta) = anchors(a, b, &self.joint);
+        let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+        let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+        let correction = solve_point(
+            a,
+            b,
+            a_offset,
+            b_offset,
+            delta * -parameters.position_correction(),
+        );
+
+        a.position -= correction * a_inv_mass;
+        a.angle -= a_offset.cross(&correction) * a_inv_inertia;
+        b.position += correction * b_inv_mass;
+        b.angle += b_offset.cross(&correction) * b_inv_inertia;
+    }
+
+    #[must_use]
+    pub fn cached_impulse(&self) -> JointImpulse {
+        self.impulse
+    }
+}
+
+/**
+ * Solves a rigid row towards zero velocity, returns the impulse applied
+ */
+fn solve_row(a: &mut Body, b: &mut Body, row: Row) -> f32 {
+    let inv_mass = row.inv_mass(a, b);
+    if inv_mass == 0f32 {
+        return 0f32;
+    }
+    let impulse = -row.velocity(a, b) / inv_mass;
+    row.apply(a, b, impulse);
+    impulse
+}
+
+impl<Broad> PhysicsWorld<Broad>
+where
+    Broad: BroadPhase<ArenaHandle> + std::fmt::Debug,
+{
+    /**
+     * Connects two bodies with a joint, waking both of them.
+     * Joints other than target joints that connect a body to itself are never solved
+     */
+    pub fn add_joint(&mut self, joint: Joint) -> JointHandle {
+        self.wake_body(joint.a);
+        self.wake_body(joint.b);
+        self.joints.insert(joint)
+    }
+
+    /**
+     * Removes a joint, the bodies it connected are woken up
+     */
+    pub fn remove_joint(&mut self, handle: JointHandle) -> Option<Joint> {
+        let joint = self.joints.remove(handle)?;
+        self.joint_cache.remove(&handle);
+        self.wake_body(joint.a);
+        self.wake_body(joint.b);
+        Some(joint)
+    }
+
+    #[must_use]
+    pub fn get_joint(&self, handle: JointHandle) -> Option<&Joint> {
+        self.joints.get(handle)
+    }
+
+    pub fn update_joint<F>(&mut self, handle: JointHandle, mut func: F)
+    where
+        F: FnMut(&mut Joint), {
+        if let Some(joint) = self.joints.get_mut(handle) {
+            func(joint);
+            let (a, b) = (joint.a, joint.b);
+            self.wake_body(a);
+            self.wake_body(b);
+        }
+    }
+
+    /**
+     * Joints attached to a body
+     */
+    #[must_use]
+    pub fn joints_of(&self, body: ArenaHandle) -> Vec<JointHandle> {
+        self.joints
+            .iter()
+            .filter(|(_, joint)| joint.a == body || joint.b == body)
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    /**
+     * Pairs of bodies connected by joints that don't let them collide
+     */
+    pub(super) fn jointed_pairs(&self) -> HashSet<CollisionPair<ArenaHandle>> {
+        self.joints
+            .iter()
+            .filter(|(_, joint)| !joint.collide_connected)
+            .map(|(_, joint)| {
+                CollisionPair {
+                    a: joint.a,
+                    b: joint.b,
+                }
+                .ordered()
+            })
+            .collect()
+    }
+
+    /**
+     * Prepares the joints for a step,
+     * joints of sleeping bodies are skipped like their contacts
+     */
+    pub(super) fn joint_constraints(&self, dt: f32) -> Vec<JointConstraint> {
+        self.joints
+            .iter()
+            .filter(|(_, joint)| {
+                !self.is_resting_pair(&CollisionPair {
+                    a: joint.a,
+                    b: joint.b,
+                })
+            })
+            .filter_map(|(handle, joint)| {
+                JointConstraint::new(&self.bodies, handle, joint, &self.joint_cache, dt)
+            })
+            .collect()
+    }
+
+    /**
+     * Keeps the impulses of a step for the next one,
+     * sleeping joints keep theirs to warm start once they wake up
+     */
+    pub(super) fn cache_joint_impulses(&mut self, constraints: &[JointConstraint]) {
+        let mut joint_cache = std::mem::take(&mut self.joint_cache);
+        joint_cache.retain(|handle, _| {
+            self.joints.get(*handle).is_some_and(|joint| {
+                self.is_resting_pair(&CollisionPair {
+                    a: joint.a,
+                    b: joint.b,
+                })
+            })
+        });
+        joint_cache.extend(
+            constraints
+                .iter()
+                .map(|constraint| (constraint.handle, constraint.cached_impulse())),
+        );
+        self.joint_cache = joint_cache;
+    }
+}
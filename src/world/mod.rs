@@ -1,104 +1,128 @@
+use std::collections::{HashMap, HashSet};
+
 use generational_arena::{Arena, Index};
 
-use self::broad::{BroadPhase, BroadPhaseElement};
+use self::{
+    broad::{BroadPhase, BroadPhaseElement},
+    joints::JointCache,
+    sleep::is_active,
+    solver::{ContactCache, ContactConstraint},
+};
 use crate::{
-    body::Body,
-    checks::{circle_vs_circle, rect_vs_circle, rect_vs_rect},
-    collision::{Collision, CollisionPair},
-    shape::Shape,
+    body::{Body, BodyType},
+    checks::shape_vs_shape,
+    collision::Collision,
+    joint::Joint,
+    shape::AABB,
     Vec2,
 };
 
 pub mod broad;
+mod joints;
+mod parameters;
+mod query;
+mod sleep;
+mod solver;
+
+pub use parameters::IntegrationParameters;
+pub use query::{RayHit, ShapeHit};
 
-// High percentage = no penetration
-const PENETRATION_PERCENTAGE: f32 = 0.5;
-// Allows penetration without jittering
-const K_SLOP: f32 = 0.01;
+pub use crate::collision::{CollisionEvent, CollisionPair};
 
 /**
- * Sets velocity in m/s
+ * Never handed out by the world, sensors are colliders with `Body::sensor` set
  */
-fn resolve_collision(bodies: &mut Arena<Body>, collision: &Collision<f32, ArenaHandle>) {
-    let (a_fixed, b_fixed, a_inv_mass, b_inv_mass, impulse_vector) = {
-        let a = bodies.get(collision.pair.a).unwrap();
-        let b = bodies.get(collision.pair.b).unwrap();
-
-        if a.fixed && b.fixed {
-            return;
-        }
-        let relative_velocity = b.velocity - a.velocity;
-        let velocity_along_normal = relative_velocity.dot(&collision.contact.normal);
-
-        if velocity_along_normal > 0f32 {
-            return;
-        }
-
-        let restitution = a.restitution.min(b.restitution);
-        let mut impulse = -(1.0 + restitution) * velocity_along_normal;
-
-        impulse = match (a.fixed, b.fixed) {
-            (false, false) => impulse / (a.inv_mass + b.inv_mass),
-            (false, true) => impulse / a.inv_mass,
-            (true, false) => impulse / b.inv_mass,
-            _ => impulse, // this will never happen
-        };
-
-        let impulse_vector = collision.contact.normal * impulse;
-
-        (a.fixed, b.fixed, a.inv_mass, b.inv_mass, impulse_vector)
-    };
-
-    if !a_fixed {
-        bodies.get_mut(collision.pair.a).unwrap().velocity -= impulse_vector * a_inv_mass;
-    }
-    if !b_fixed {
-        bodies.get_mut(collision.pair.b).unwrap().velocity += impulse_vector * b_inv_mass;
-    }
+#[deprecated(note = "set `Body::sensor` to make a body a sensor")]
+#[derive(Debug, Clone)]
+pub struct SensorHandle {
+    pub index: usize,
 }
 
-fn correct_position(bodies: &mut Arena<Body>, collision: &Collision<f32, ArenaHandle>) {
-    let (a_fixed, b_fixed, a_inv_mass, b_inv_mass, correction) = {
-        let a = bodies.get(collision.pair.a).unwrap();
-        let b = bodies.get(collision.pair.b).unwrap();
-
-        if a.fixed && b.fixed {
-            return;
-        }
-
-        let maximum = 0f32.max(collision.contact.penetration_depth - K_SLOP);
+pub type ArenaHandle = Index;
+pub type JointHandle = Index;
 
-        let correction_scalar = if b.fixed {
-            maximum / a.inv_mass * PENETRATION_PERCENTAGE
-        } else if a.fixed {
-            maximum / b.inv_mass * PENETRATION_PERCENTAGE
-        } else {
-            maximum / (a.inv_mass + b.inv_mass) * PENETRATION_PERCENTAGE
-        };
+/**
+ * A pair of bodies together with the colliders of them that touch
+ */
+type ActivePair = (CollisionPair<ArenaHandle>, (usize, usize));
 
-        let correction = collision.contact.normal * correction_scalar;
-        (a.fixed, b.fixed, a.inv_mass, b.inv_mass, correction)
-    };
-    if !a_fixed {
-        bodies.get_mut(collision.pair.a).unwrap().position -= correction * a_inv_mass;
-    }
-    if !b_fixed {
-        bodies.get_mut(collision.pair.b).unwrap().position += correction * b_inv_mass;
-    }
+/**
+ * Whether any collider of a overlaps any collider of b
+ */
+fn bodies_overlap(a: &Body, b: &Body) -> bool {
+    a.placed_colliders().any(|a_collider| {
+        b.placed_colliders().any(|b_collider| {
+            shape_vs_shape(
+                a_collider.shape,
+                b_collider.shape,
+                a_collider.position,
+                b_collider.position,
+                a_collider.angle,
+                b_collider.angle,
+            )
+            .is_some()
+        })
+    })
 }
 
-#[derive(Debug, Clone)]
-pub struct SensorHandle {
-    pub index: usize,
+/**
+ * Result of `PhysicsWorld::update_fixed`
+ */
+#[derive(Debug)]
+pub struct FixedUpdate {
+    /**
+     * Collisions of every step taken
+     */
+    pub collisions: Vec<Collision<f32, ArenaHandle>>,
+    /**
+     * Contact events of every step taken
+     */
+    pub events:     Vec<CollisionEvent<ArenaHandle>>,
+    pub steps:      usize,
+    /**
+     * Fraction of a step left in the accumulator,
+     * used to interpolate bodies between the previous and current step
+     */
+    pub alpha:      f32,
 }
 
-pub type ArenaHandle = Index;
-
 pub struct PhysicsWorld<Broad>
 where
     Broad: BroadPhase<ArenaHandle>, {
-    pub bodies:      Arena<Body>,
-    pub broad_phase: Broad,
+    pub bodies:        Arena<Body>,
+    pub broad_phase:   Broad,
+    /**
+     * Acceleration applied to every dynamic body,
+     * scaled by `Body::gravity_scale`
+     */
+    pub gravity:       Vec2<f32>,
+    parameters:        IntegrationParameters,
+    contact_cache:     ContactCache,
+    joints:            Arena<Joint>,
+    joint_cache:       JointCache,
+    accumulator:       f32,
+    /**
+     * Bounding boxes the bodies are stored with in the broad phase
+     */
+    broad_phase_aabbs: HashMap<ArenaHandle, AABB<i32>>,
+    /**
+     * Colliders touching in the last step and whether they involve a sensor
+     */
+    active_pairs:      HashMap<ActivePair, bool>,
+    events:            Vec<CollisionEvent<ArenaHandle>>,
+    /**
+     * Island every sleeping body fell asleep in, woken up together
+     */
+    sleeping_islands:  HashMap<ArenaHandle, usize>,
+    /**
+     * Sleeping bodies of every island
+     */
+    islands:           HashMap<usize, Vec<ArenaHandle>>,
+    next_island:       usize,
+    /**
+     * Gravity of the last step, sleeping bodies wake up when it changes
+     */
+    stepped_gravity:   Vec2<f32>,
 }
 
 impl<Broad> PhysicsWorld<Broad>
@@ -109,38 +133,126 @@ where
         Self {
             bodies: Arena::new(),
             broad_phase,
+            gravity: Vec2::new(0f32, 0f32),
+            parameters: IntegrationParameters::default(),
+            contact_cache: ContactCache::new(),
+            joints: Arena::new(),
+            joint_cache: JointCache::new(),
+            accumulator: 0f32,
+            broad_phase_aabbs: HashMap::new(),
+            active_pairs: HashMap::new(),
+            events: vec![],
+            sleeping_islands: HashMap::new(),
+            islands: HashMap::new(),
+            next_island: 0,
+            stepped_gravity: Vec2::new(0f32, 0f32),
         }
     }
 
-    pub fn add_body(&mut self, body: Body) -> ArenaHandle {
+    #[must_use]
+    pub fn integration_parameters(&self) -> &IntegrationParameters {
+        &self.parameters
+    }
+
+    pub fn integration_parameters_mut(&mut self) -> &mut IntegrationParameters {
+        &mut self.parameters
+    }
+
+    pub fn set_integration_parameters(&mut self, parameters: IntegrationParameters) {
+        self.parameters = parameters;
+    }
+
+    pub fn add_body(&mut self, mut body: Body) -> ArenaHandle {
+        body.previous_position = body.position;
+        body.previous_angle = body.angle;
         let aabb = body.get_aabb();
         let handle = self.bodies.insert(body);
         self.broad_phase.insert(BroadPhaseElement { aabb, handle });
+        self.broad_phase_aabbs.insert(handle, aabb);
 
         handle
     }
 
+    /**
+     * Removes a body together with its joints
+     *
+     * # Panics
+     * Panics if the body was already removed
+     */
     pub fn remove_body(&mut self, handle: &ArenaHandle) {
+        // Bodies resting on the removed one have to fall
+        self.wake_body(*handle);
+        let touching: Vec<ArenaHandle> = self
+            .active_pairs
+            .keys()
+            .filter_map(|(pair, _)| {
+                if pair.a == *handle {
+                    Some(pair.b)
+                } else if pair.b == *handle {
+                    Some(pair.a)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for other in touching {
+            self.wake_body(other);
+        }
+        for joint in self.joints_of(*handle) {
+            self.remove_joint(joint);
+        }
+
         let body = self.bodies.remove(*handle).unwrap();
+        let aabb = self
+            .broad_phase_aabbs
+            .remove(handle)
+            .unwrap_or_else(|| body.get_aabb());
         self.broad_phase.remove(BroadPhaseElement {
             handle: *handle,
-            aabb:   body.get_aabb(),
+            aabb,
         });
     }
 
+    /**
+     * Changes a body in place and moves its broad phase entry along
+     *
+     * # Panics
+     * Panics if the body was removed
+     */
     pub fn update<F>(&mut self, handle: &ArenaHandle, mut func: F)
     where
         F: FnMut(&mut Body), {
-        let body = self.bodies.get_mut(*handle).unwrap();
-        self.broad_phase.remove(BroadPhaseElement {
-            aabb:   body.get_aabb(),
-            handle: *handle,
-        });
-        func(body);
-        self.broad_phase.insert(BroadPhaseElement {
-            aabb:   body.get_aabb(),
-            handle: *handle,
-        });
+        self.wake_body(*handle);
+        func(self.bodies.get_mut(*handle).unwrap());
+        self.sync_broad_phase(*handle);
+    }
+
+    /**
+     * Moves the broad phase entry of a body if its bounding box changed
+     */
+    fn sync_broad_phase(&mut self, handle: ArenaHandle) {
+        let aabb = self.bodies.get(handle).unwrap().get_aabb();
+        match self.broad_phase_aabbs.insert(handle, aabb) {
+            Some(previous) if previous == aabb => {},
+            Some(previous) => {
+                self.broad_phase.move_element(
+                    BroadPhaseElement {
+                        aabb: previous,
+                        handle,
+                    },
+                    aabb,
+                );
+            },
+            None => self.broad_phase.insert(BroadPhaseElement { aabb, handle }),
+        }
+    }
+
+    /**
+     * Contact events of the last step
+     */
+    #[must_use]
+    pub fn events(&self) -> &[CollisionEvent<ArenaHandle>] {
+        &self.events
     }
 
     #[must_use]
@@ -150,101 +262,1535 @@ where
     }
 
     fn calc_velocity(&mut self, dt: f32) {
-        // Update position of bodies based on velocity
+        let gravity = self.gravity;
+        let parameters = self.parameters;
+        // Update velocity of bodies based on forces
         for (_, body) in &mut self.bodies {
-            // TODO: Fix force code
-            // this is not really using any fancy physics, it's just me (???!!!)
-            let linear_acceleration = body.force / body.mass;
-            body.velocity += linear_acceleration * dt;
+            if body.sleeping {
+                continue;
+            }
+            match body.body_type {
+                BodyType::Dynamic => {
+                    // TODO: Fix force code
+                    // this is not really using any fancy physics, it's just me (???!!!)
+                    let linear_acceleration = body.force * body.inv_mass;
+                    body.velocity += linear_acceleration * dt;
+                    // Bodies without a finite mass only move with their velocity
+                    if body.inv_mass > 0f32 {
+                        body.velocity += gravity * body.gravity_scale * dt;
+                    }
+
+                    let linear_damping = body
+                        .linear_damping
+                        .unwrap_or_else(|| parameters.default_linear_damping());
+                    let damping = body.velocity * linear_damping * dt;
+                    body.velocity -= damping;
+
+                    let angular_acceleration = body.torque * body.inv_inertia;
+                    body.angular_velocity += angular_acceleration * dt;
+                    let angular_damping = body
+                        .angular_damping
+                        .unwrap_or_else(|| parameters.default_angular_damping());
+                    body.angular_velocity -= body.angular_velocity * angular_damping * dt;
+                },
+                BodyType::KinematicPosition => {
+                    // The velocity that reaches the target within the step,
+                    // lets contacts push dynamic bodies along.
+                    // A step without length leaves the velocity as it is
+                    if let Some((position, angle)) = body.kinematic_target.filter(|_| dt > 0f32) {
+                        body.velocity = (position - body.position) / dt;
+                        body.angular_velocity = (angle - body.angle) / dt;
+                    }
+                },
+                BodyType::Fixed | BodyType::KinematicVelocity => {},
+            }
             // Force has been applied, reset it in body
             body.force = Vec2::new(0f32, 0f32);
+            body.torque = 0f32;
+        }
+    }
 
-            // Apply friction based on surface
-            let friction_val = body.velocity * body.friction * dt;
-            body.velocity -= friction_val;
-
-            if body.velocity.abs() < Vec2::new(0.1, 0.1) {
+    /**
+     * Moves bodies by their velocity, after the contacts have been solved
+     * so that resting bodies don't sink into each other first
+     */
+    fn calc_position(&mut self, dt: f32, collisions: &[Collision<f32, ArenaHandle>]) {
+        let threshold = self.parameters.rest_velocity_threshold();
+        // Only bodies in contact are brought to rest, free bodies have to be able
+        // to start falling even when gravity adds less than the threshold per step
+        let touching: HashSet<ArenaHandle> = collisions
+            .iter()
+            .filter(|collision| !collision.sensor)
+            .flat_map(|collision| [collision.pair.a, collision.pair.b])
+            .collect();
+        for (handle, body) in &mut self.bodies {
+            // Compared per axis, the ordering of `Vec2` is lexicographic
+            // and would stop bodies that only move along y
+            if body.body_type.is_dynamic()
+                && touching.contains(&handle)
+                && body.velocity.x.abs() < threshold
+                && body.velocity.y.abs() < threshold
+            {
                 body.velocity = Vec2::new(0f32, 0f32);
             }
+        }
 
-            body.position += body.velocity * dt;
+        // Swept while every body is still where the broad phase has it
+        let impacts: HashMap<ArenaHandle, f32> = self
+            .bodies
+            .iter()
+            .filter(|(_, body)| body.ccd && body.body_type.is_dynamic() && !body.sensor)
+            .filter_map(|(handle, _)| Some((handle, self.time_of_impact(handle, dt)?)))
+            .collect();
+
+        for (handle, body) in &mut self.bodies {
+            if body.sleeping || body.body_type.is_fixed() {
+                continue;
+            }
+            let toi = impacts.get(&handle).copied().unwrap_or(1f32);
+            body.position += body.velocity * dt * toi;
+            body.angle += body.angular_velocity * dt;
         }
     }
 
-    pub fn update_with_quad(&mut self, dt: f32) -> Vec<Collision<f32, ArenaHandle>> {
-        self.calc_velocity(dt);
-        // Broad phase
-        let broad_collisions = self.broad_phase.check_collisions();
-        // Narrow phase
-        let collisions =
-            broad_collisions
-                .iter()
-                .fold(vec![], |mut narrow_collisions, collision| {
-                    let a_body = self.bodies.get(collision.a).unwrap();
-                    let b_body = self.bodies.get(collision.b).unwrap();
-
-                    let maybe_contact = match (&a_body.shape, &b_body.shape) {
-                        (Shape::Circle(a_circle), Shape::Circle(b_circle)) => {
-                            circle_vs_circle(a_circle, b_circle, a_body.position, b_body.position)
-                        },
-                        (Shape::Rect(a_rect), Shape::Rect(b_rect)) => {
-                            rect_vs_rect(a_rect, b_rect, &a_body.position, &b_body.position)
-                        },
-                        (Shape::Circle(circle), Shape::Rect(rect))
-                        | (Shape::Rect(rect), Shape::Circle(circle)) => {
-                            rect_vs_circle(rect, circle, &a_body.position, &b_body.position)
-                        },
-                    };
-
-                    if let Some(contact) = maybe_contact {
-                        narrow_collisions.push(Collision {
-                            pair: *collision,
-                            contact,
-                        });
-                    }
-                    narrow_collisions
-                });
+    /**
+     * Fraction of the step a body can move before it hits another body,
+     * bodies it already touches are left to the solver
+     */
+    fn time_of_impact(&self, handle: ArenaHandle, dt: f32) -> Option<f32> {
+        let body = self.bodies.get(handle)?;
+        body.placed_colliders()
+            .filter(|collider| !collider.sensor)
+            .filter_map(|collider| {
+                self.cast_rotated_shape(
+                    collider.shape,
+                    collider.position,
+                    collider.angle,
+                    body.velocity * dt,
+                    false,
+                    |other_handle, other| {
+                        other_handle != handle
+                            && !other.sensor
+                            && body.can_collide_with(other)
+                            && !bodies_overlap(body, other)
+                    },
+                )
+            })
+            .map(|hit| hit.toi)
+            .min_by(f32::total_cmp)
+    }
 
-        for collision in &collisions {
-            let a_sensor_or_fixed = self
-                .get_body(collision.pair.a)
-                .map_or(false, |a_body| a_body.fixed || a_body.sensor);
-            let b_sensor_or_fixed = self
-                .get_body(collision.pair.b)
-                .map_or(false, |b_body| b_body.fixed || b_body.sensor);
-
-            if !a_sensor_or_fixed {
-                self.broad_phase.remove(BroadPhaseElement {
-                    aabb:   self.bodies.get(collision.pair.a).unwrap().get_aabb(),
-                    handle: collision.pair.a,
-                });
+    /**
+     * Advances the world by `dt` seconds in fixed steps,
+     * time left over is accumulated for the next update
+     */
+    pub fn update_fixed(&mut self, dt: f32) -> FixedUpdate {
+        let timestep = self.parameters.timestep();
+        let substeps = self.parameters.substeps();
+
+        self.accumulator += dt;
+        let mut collisions = vec![];
+        let mut events = vec![];
+        let mut steps = 0;
+        // Substep counts are far too small to lose precision as a float
+        #[allow(clippy::cast_precision_loss)]
+        let substep = timestep / substeps as f32;
+        while self.accumulator >= timestep && steps < self.parameters.max_steps() {
+            for (_, body) in &mut self.bodies {
+                body.previous_position = body.position;
+                body.previous_angle = body.angle;
             }
-            if !b_sensor_or_fixed {
-                self.broad_phase.remove(BroadPhaseElement {
-                    aabb:   self.bodies.get(collision.pair.b).unwrap().get_aabb(),
-                    handle: collision.pair.b,
-                });
+            for _ in 0..substeps {
+                collisions.append(&mut self.update_with_quad(substep));
+                events.extend_from_slice(&self.events);
             }
+            self.accumulator -= timestep;
+            steps += 1;
+        }
+        // Drop the time that didn't fit in the maximum number of steps,
+        // otherwise every following update would fall further behind
+        if self.accumulator >= timestep {
+            self.accumulator %= timestep;
+        }
+
+        FixedUpdate {
+            collisions,
+            events,
+            steps,
+            alpha: self.accumulator / timestep,
+        }
+    }
+
+    /**
+     * Whether neither body of a pair is simulated, their contact
+     * stays the same as long as both exist
+     */
+    fn is_resting_pair(&self, pair: &CollisionPair<ArenaHandle>) -> bool {
+        match (self.bodies.get(pair.a), self.bodies.get(pair.b)) {
+            (Some(a), Some(b)) => !is_active(a) && !is_active(b),
+            _ => false,
+        }
+    }
+
+    /**
+     * Compares the collisions of a step with the pairs that were touching
+     * before, pairs of removed bodies end as they are no longer found.
+     * Pairs of sleeping bodies aren't checked and silently keep touching
+     */
+    fn update_events(&mut self, collisions: &[Collision<f32, ArenaHandle>]) {
+        let mut previous_pairs = std::mem::take(&mut self.active_pairs);
+        self.events.clear();
 
-            resolve_collision(&mut self.bodies, collision);
-            correct_position(&mut self.bodies, collision);
+        let resting_pairs: Vec<_> = previous_pairs
+            .iter()
+            .filter(|((pair, _), _)| self.is_resting_pair(pair))
+            .map(|(active_pair, sensor)| (*active_pair, *sensor))
+            .collect();
+        for (active_pair, sensor) in resting_pairs {
+            previous_pairs.remove(&active_pair);
+            self.active_pairs.insert(active_pair, sensor);
+        }
 
-            if !a_sensor_or_fixed {
-                self.broad_phase.insert(BroadPhaseElement {
-                    handle: collision.pair.a,
-                    aabb:   self.get_body(collision.pair.a).unwrap().get_aabb(),
+        for collision in collisions {
+            let (pair, colliders, sensor) = (collision.pair, collision.colliders, collision.sensor);
+            self.events
+                .push(if previous_pairs.contains_key(&(pair, colliders)) {
+                    CollisionEvent::Persisted {
+                        pair,
+                        colliders,
+                        sensor,
+                    }
+                } else {
+                    CollisionEvent::Started {
+                        pair,
+                        colliders,
+                        sensor,
+                    }
                 });
+            self.active_pairs.insert((pair, colliders), sensor);
+        }
+
+        let mut ended: Vec<_> = previous_pairs
+            .into_iter()
+            .filter(|(active_pair, _)| !self.active_pairs.contains_key(active_pair))
+            .collect();
+        ended.sort_unstable_by_key(|((pair, colliders), _)| (pair.a, pair.b, *colliders));
+        self.events
+            .extend(
+                ended
+                    .into_iter()
+                    .map(|((pair, colliders), sensor)| CollisionEvent::Ended {
+                        pair,
+                        colliders,
+                        sensor,
+                    }),
+            );
+    }
+
+    /**
+     * Checks every collider of the first body against every collider of the second
+     */
+    fn narrow_phase(
+        &self,
+        pairs: &[CollisionPair<ArenaHandle>],
+    ) -> Vec<Collision<f32, ArenaHandle>> {
+        let mut collisions = vec![];
+        for pair in pairs {
+            let a_body = self.bodies.get(pair.a).unwrap();
+            let b_body = self.bodies.get(pair.b).unwrap();
+
+            for a_collider in a_body.placed_colliders() {
+                for b_collider in b_body.placed_colliders() {
+                    if let Some(manifold) = shape_vs_shape(
+                        a_collider.shape,
+                        b_collider.shape,
+                        a_collider.position,
+                        b_collider.position,
+                        a_collider.angle,
+                        b_collider.angle,
+                    ) {
+                        collisions.push(Collision {
+                            pair: *pair,
+                            manifold,
+                            colliders: (a_collider.index, b_collider.index),
+                            sensor: a_collider.sensor || b_collider.sensor,
+                        });
+                    }
+                }
             }
-            if !b_sensor_or_fixed {
-                self.broad_phase.insert(BroadPhaseElement {
-                    handle: collision.pair.b,
-                    aabb:   self.get_body(collision.pair.b).unwrap().get_aabb(),
-                });
+        }
+        collisions
+    }
+
+    /**
+     * Advances the world by a single step of length `dt`
+     *
+     * # Panics
+     * Panics if the broad phase still holds a removed body
+     */
+    pub fn update_with_quad(&mut self, dt: f32) -> Vec<Collision<f32, ArenaHandle>> {
+        self.wake_on_gravity_change();
+        self.calc_velocity(dt);
+        let jointed_pairs = self.jointed_pairs();
+        // Broad phase
+        // Pairs are ordered to find their cached impulses, a pair reported
+        // by several nodes of the broad phase is only checked once.
+        // They are also sorted so the solver runs in the same order every step
+        let mut broad_collisions: Vec<CollisionPair<ArenaHandle>> = self
+            .broad_phase
+            .check_collisions()
+            .into_iter()
+            .map(CollisionPair::ordered)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|pair| {
+                let a_body = self.bodies.get(pair.a).unwrap();
+                let b_body = self.bodies.get(pair.b).unwrap();
+                a_body.can_collide_with(b_body) && !jointed_pairs.contains(pair)
+            })
+            .collect();
+        broad_collisions.sort_unstable_by_key(|pair| (pair.a, pair.b));
+        // Pairs of sleeping or fixed bodies are skipped,
+        // unless one of them is woken up by a contact of this step
+        let (mut resting_pairs, broad_collisions): (Vec<_>, Vec<_>) = broad_collisions
+            .into_iter()
+            .partition(|pair| self.is_resting_pair(pair));
+        let mut collisions = self.narrow_phase(&broad_collisions);
+        if self.wake_touched(&collisions) {
+            resting_pairs.retain(|pair| !self.is_resting_pair(pair));
+            collisions.append(&mut self.narrow_phase(&resting_pairs));
+            collisions.sort_unstable_by_key(|collision| {
+                (collision.pair.a, collision.pair.b, collision.colliders)
+            });
+        }
+        self.update_events(&collisions);
+
+        let mut constraints: Vec<ContactConstraint> = collisions
+            .iter()
+            .filter_map(|collision| {
+                ContactConstraint::new(
+                    &self.bodies,
+                    collision,
+                    &self.contact_cache,
+                    &self.parameters,
+                )
+            })
+            .collect();
+        let mut joint_constraints = self.joint_constraints(dt);
+
+        for constraint in &joint_constraints {
+            constraint.warm_start(&mut self.bodies, &self.parameters);
+        }
+        for constraint in &constraints {
+            constraint.warm_start(&mut self.bodies);
+        }
+        for _ in 0..self.parameters.velocity_iterations() {
+            for constraint in &mut joint_constraints {
+                constraint.solve_velocity(&mut self.bodies);
+            }
+            for constraint in &mut constraints {
+                constraint.solve_velocity(&mut self.bodies);
             }
         }
+        self.calc_position(dt, &collisions);
+        for _ in 0..self.parameters.position_iterations() {
+            for constraint in &joint_constraints {
+                constraint.solve_position(&mut self.bodies, &self.parameters);
+            }
+            for constraint in &constraints {
+                constraint.solve_position(&mut self.bodies, &self.parameters);
+            }
+        }
+
+        // Sleeping contacts keep their impulses to warm start once they wake up
+        let mut contact_cache = std::mem::take(&mut self.contact_cache);
+        contact_cache.retain(|(pair, _), _| self.is_resting_pair(pair));
+        contact_cache.extend(constraints.iter().map(|constraint| {
+            (
+                (constraint.pair, constraint.colliders),
+                constraint.cached_impulses(),
+            )
+        }));
+        self.contact_cache = contact_cache;
+        self.cache_joint_impulses(&joint_constraints);
+        self.update_islands(&collisions, dt);
+
+        let handles: Vec<ArenaHandle> = self
+            .bodies
+            .iter()
+            .filter(|(_, body)| !body.sleeping)
+            .map(|(handle, _)| handle)
+            .collect();
+        for handle in handles {
+            self.sync_broad_phase(handle);
+        }
 
         self.broad_phase.clean_up();
 
         collisions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        body::ColliderPart,
+        joint::Joint,
+        shape::{Circle, Polygon, Shape, AABB},
+        QuadTree,
+    };
+
+    fn world() -> PhysicsWorld<QuadTree<ArenaHandle>> {
+        PhysicsWorld::new(QuadTree::new(0, AABB::new(-100, -100, 200, 200)))
+    }
+
+    fn square(size: f32) -> Shape {
+        let half = size / 2f32;
+        Shape::Polygon(Polygon::new(vec![
+            Vec2::new(-half, -half),
+            Vec2::new(half, -half),
+            Vec2::new(half, half),
+            Vec2::new(-half, half),
+        ]))
+    }
+
+    #[test]
+    fn it_spins_bodies_hit_off_center() {
+        let mut world = world();
+        let mut body = Body {
+            shape: square(2.0),
+            velocity: Vec2::new(1.0, -10.0),
+            ..Body::default()
+        };
+        body.compute_inertia();
+        let handle = world.add_body(body);
+        world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.5)),
+            position: Vec2::new(0.8, -1.4),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+
+        world.update_with_quad(1.0 / 60.0);
+
+        assert!(world.get_body(handle).unwrap().angular_velocity > 0.0);
+    }
+
+    #[test]
+    fn it_rests_boxes_without_spinning() {
+        let mut world = world();
+        let mut body = Body {
+            shape: Shape::Rect(Vec2::new(2.0, 2.0)),
+            position: Vec2::new(0.5, 1.95),
+            ..Body::default()
+        };
+        body.compute_inertia();
+        let handle = world.add_body(body);
+        world.add_body(Body {
+            shape: Shape::Rect(Vec2::new(10.0, 2.0)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+
+        for _ in 0..60 {
+            world.update(&handle, |body| body.velocity.y -= 1.0);
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        let body = world.get_body(handle).unwrap();
+        assert!(body.angle.abs() < 1e-3);
+        assert!(body.position.y > 1.9);
+    }
+
+    #[test]
+    fn it_applies_scaled_gravity() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let falling = world.add_body(Body::default());
+        let rising = world.add_body(Body {
+            position: Vec2::new(10.0, 0.0),
+            gravity_scale: -0.5,
+            ..Body::default()
+        });
+        let fixed = world.add_body(Body {
+            position: Vec2::new(-10.0, 0.0),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+
+        world.update_with_quad(1.0);
+
+        assert_eq!(
+            world.get_body(falling).unwrap().velocity,
+            Vec2::new(0.0, -10.0)
+        );
+        assert_eq!(
+            world.get_body(rising).unwrap().velocity,
+            Vec2::new(0.0, 5.0)
+        );
+        assert_eq!(world.get_body(fixed).unwrap().velocity, Vec2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn it_starts_falling_at_small_timesteps() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let handle = world.add_body(Body {
+            position: Vec2::new(0.0, 50.0),
+            ..Body::default()
+        });
+
+        // Gravity adds less than the rest velocity threshold every step
+        for _ in 0..120 {
+            world.update_with_quad(1.0 / 120.0);
+        }
+
+        let body = world.get_body(handle).unwrap();
+        assert!(body.velocity.y < -9.0, "{:?}", body.velocity);
+        assert!(body.position.y < 46.0, "{:?}", body.position);
+    }
+
+    #[test]
+    fn it_applies_coulomb_friction() {
+        let slide = |friction: f32, static_friction: f32| {
+            let mut world = world();
+            let handle = world.add_body(Body {
+                position: Vec2::new(0.0, 1.99),
+                velocity: Vec2::new(5.0, -1.0),
+                shape: Shape::Rect(Vec2::new(2.0, 2.0)),
+                friction,
+                static_friction,
+                ..Body::default()
+            });
+            world.add_body(Body {
+                shape: Shape::Rect(Vec2::new(20.0, 2.0)),
+                body_type: BodyType::Fixed,
+                friction: 1.0,
+                static_friction: 1.0,
+                ..Body::default()
+            });
+
+            world.update_with_quad(1.0 / 60.0);
+            world.get_body(handle).unwrap().velocity.x
+        };
+
+        // A small normal impulse can't hold a fast body, it keeps sliding
+        let sliding = slide(0.5, 0.5);
+        assert!(sliding > 4.0 && sliding < 5.0);
+        assert!((slide(0.0, 0.0) - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn it_passes_through_sensors() {
+        let mut world = world();
+        let handle = world.add_body(Body {
+            position: Vec2::new(-1.5, 0.0),
+            velocity: Vec2::new(10.0, 0.0),
+            linear_damping: Some(0.0),
+            ..Body::default()
+        });
+        let sensor = world.add_body(Body {
+            shape: square(2.0),
+            sensor: true,
+            ..Body::default()
+        });
+
+        let collisions = world.update_with_quad(1.0 / 60.0);
+
+        assert_eq!(collisions.len(), 1);
+        assert!(collisions[0].sensor);
+        let body = world.get_body(handle).unwrap();
+        assert_eq!(body.velocity, Vec2::new(10.0, 0.0));
+        let sensor = world.get_body(sensor).unwrap();
+        assert_eq!(sensor.velocity, Vec2::new(0.0, 0.0));
+        assert_eq!(sensor.position, Vec2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn it_reports_sensor_events() {
+        let mut world = world();
+        let handle = world.add_body(Body {
+            position: Vec2::new(-2.5, 0.0),
+            velocity: Vec2::new(60.0, 0.0),
+            linear_damping: Some(0.0),
+            ..Body::default()
+        });
+        let sensor = world.add_body(Body {
+            shape: square(2.0),
+            sensor: true,
+            ..Body::default()
+        });
+        let pair = CollisionPair {
+            a: handle,
+            b: sensor,
+        }
+        .ordered();
+
+        let mut events = vec![];
+        for _ in 0..6 {
+            world.update_with_quad(1.0 / 60.0);
+            events.extend_from_slice(world.events());
+        }
+
+        assert_eq!(events, vec![
+            CollisionEvent::Started {
+                pair,
+                colliders: (0, 0),
+                sensor: true,
+            },
+            CollisionEvent::Persisted {
+                pair,
+                colliders: (0, 0),
+                sensor: true,
+            },
+            CollisionEvent::Persisted {
+                pair,
+                colliders: (0, 0),
+                sensor: true,
+            },
+            CollisionEvent::Persisted {
+                pair,
+                colliders: (0, 0),
+                sensor: true,
+            },
+            CollisionEvent::Ended {
+                pair,
+                colliders: (0, 0),
+                sensor: true,
+            },
+        ]);
+    }
+
+    #[test]
+    fn it_filters_collisions_by_layers() {
+        let overlapping = |a: Body, b: Body| {
+            let mut world = world();
+            world.add_body(a);
+            world.add_body(Body {
+                position: Vec2::new(1.0, 0.0),
+                ..b
+            });
+            !world.update_with_quad(1.0 / 60.0).is_empty()
+        };
+        let layers = |memberships: u32, filter: u32, group: Option<i32>| Body {
+            memberships,
+            filter,
+            group,
+            ..Body::default()
+        };
+
+        assert!(overlapping(Body::default(), Body::default()));
+        assert!(overlapping(
+            layers(0b01, 0b10, None),
+            layers(0b10, 0b01, None)
+        ));
+        assert!(!overlapping(
+            layers(0b01, 0b10, None),
+            layers(0b10, 0b10, None)
+        ));
+        assert!(!overlapping(
+            layers(0b01, 0b01, None),
+            layers(0b10, 0b10, None)
+        ));
+        assert!(overlapping(
+            layers(0b01, 0b01, Some(1)),
+            layers(0b10, 0b10, Some(1))
+        ));
+        assert!(overlapping(
+            Body::default(),
+            layers(u32::MAX, u32::MAX, Some(-1))
+        ));
+        assert!(!overlapping(
+            layers(u32::MAX, u32::MAX, Some(-1)),
+            layers(u32::MAX, u32::MAX, Some(-1))
+        ));
+    }
+
+    #[test]
+    fn it_casts_rays() {
+        let mut world = world();
+        let near = world.add_body(Body {
+            position: Vec2::new(5.0, 0.0),
+            ..Body::default()
+        });
+        let far = world.add_body(Body {
+            position: Vec2::new(10.0, 0.0),
+            shape: Shape::Rect(Vec2::new(2.0, 2.0)),
+            ..Body::default()
+        });
+        world.add_body(Body {
+            position: Vec2::new(5.0, 5.0),
+            ..Body::default()
+        });
+
+        let origin = Vec2::new(0.0, 0.0);
+        let direction = Vec2::new(1.0, 0.0);
+        let hit = world
+            .cast_ray(origin, direction, 100.0, |_, _| true)
+            .unwrap();
+        assert_eq!(hit.handle, near);
+        assert!((hit.toi - 4.0).abs() < 1e-5);
+        assert_eq!(hit.point, Vec2::new(4.0, 0.0));
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+        let hit = world
+            .cast_ray(origin, direction, 100.0, |handle, _| handle != near)
+            .unwrap();
+        assert_eq!(hit.handle, far);
+        assert!((hit.toi - 9.0).abs() < 1e-5);
+
+        let hits = world.cast_ray_all(origin, direction, 100.0, |_, _| true);
+        assert_eq!(hits.iter().map(|hit| hit.handle).collect::<Vec<_>>(), vec![
+            near, far
+        ]);
+        assert!(world
+            .cast_ray(origin, direction, 3.0, |_, _| true)
+            .is_none());
+    }
+
+    #[test]
+    fn it_casts_shapes() {
+        let mut world = world();
+        let wall = world.add_body(Body {
+            position: Vec2::new(10.0, 0.0),
+            shape: Shape::Rect(Vec2::new(2.0, 10.0)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+
+        let circle = Shape::Circle(Circle::new(1.0));
+        let hit = world
+            .cast_shape(
+                &circle,
+                Vec2::new(0.0, 0.0),
+                Vec2::new(16.0, 0.0),
+                |_, _| true,
+            )
+            .unwrap();
+        assert_eq!(hit.handle, wall);
+        assert!((hit.toi - 0.5).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::new(-1.0, 0.0));
+
+        // Landing on top of the wall
+        let hit = world
+            .cast_shape(
+                &square(2.0),
+                Vec2::new(9.0, 8.0),
+                Vec2::new(2.0, -4.0),
+                |_, _| true,
+            )
+            .unwrap();
+        assert!((hit.toi - 0.5).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::new(0.0, 1.0));
+
+        assert!(world
+            .cast_shape(
+                &circle,
+                Vec2::new(0.0, 0.0),
+                Vec2::new(0.0, 16.0),
+                |_, _| true
+            )
+            .is_none());
+        assert!(world
+            .cast_shape(
+                &circle,
+                Vec2::new(0.0, 0.0),
+                Vec2::new(16.0, 0.0),
+                |_, _| false
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn it_queries_bodies() {
+        let mut world = world();
+        let circle = world.add_body(Body::default());
+        let rect = world.add_body(Body {
+            position: Vec2::new(3.0, 0.0),
+            shape: Shape::Rect(Vec2::new(2.0, 2.0)),
+            angle: std::f32::consts::FRAC_PI_4,
+            ..Body::default()
+        });
+        let all = |_: ArenaHandle, _: &Body| true;
+
+        assert_eq!(world.bodies_at_point(Vec2::new(0.5, 0.5), all), vec![
+            circle
+        ]);
+        // Inside the bounding box of both bodies, but outside their shapes
+        assert!(world.bodies_at_point(Vec2::new(1.9, 0.95), all).is_empty());
+        assert_eq!(world.bodies_at_point(Vec2::new(3.0, 1.3), all), vec![rect]);
+
+        assert_eq!(
+            world.intersect_shape(&Shape::Circle(Circle::new(0.5)), Vec2::new(1.5, 0.0), all),
+            vec![circle, rect]
+        );
+        assert_eq!(
+            world.intersect_shape(
+                &Shape::Circle(Circle::new(0.5)),
+                Vec2::new(1.5, 0.0),
+                |handle, _| handle != circle
+            ),
+            vec![rect]
+        );
+
+        let aabb = AABB {
+            min: Vec2::new(2.9, -5.0),
+            max: Vec2::new(10.0, 5.0),
+        };
+        assert_eq!(world.bodies_in_aabb(aabb, all), vec![rect]);
+    }
+
+    #[test]
+    fn it_stops_fast_bodies_at_thin_walls() {
+        let shoot = |ccd: bool| {
+            let mut world = world();
+            let bullet = world.add_body(Body {
+                velocity: Vec2::new(600.0, 0.0),
+                shape: Shape::Circle(Circle::new(0.1)),
+                linear_damping: Some(0.0),
+                ccd,
+                ..Body::default()
+            });
+            world.add_body(Body {
+                position: Vec2::new(5.0, 0.0),
+                shape: Shape::Rect(Vec2::new(0.2, 10.0)),
+                body_type: BodyType::Fixed,
+                ..Body::default()
+            });
+            for _ in 0..3 {
+                world.update_with_quad(1.0 / 60.0);
+            }
+            world.get_body(bullet).unwrap().position.x
+        };
+
+        assert!(shoot(false) > 5.0);
+        assert!(shoot(true) < 5.0);
+    }
+
+    #[test]
+    fn it_puts_resting_islands_to_sleep() {
+        let (mut world, top) = stack(&Shape::Rect(Vec2::new(1.0, 1.0)), 3);
+        world.gravity = Vec2::new(0.0, -10.0);
+        for _ in 0..120 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+        assert!(world.is_sleeping(top));
+        let position = world.get_body(top).unwrap().position;
+
+        // Sleeping bodies ignore gravity but wake up when something lands on them
+        for _ in 0..10 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+        assert_eq!(world.get_body(top).unwrap().position, position);
+        let ball = world.add_body(Body {
+            position: Vec2::new(0.0, 6.0),
+            ..Body::default()
+        });
+        let mut woke = false;
+        for _ in 0..60 {
+            world.update_with_quad(1.0 / 60.0);
+            woke |= !world.is_sleeping(top);
+        }
+        assert!(woke);
+        assert!(world.get_body(ball).unwrap().position.y > 3.0);
+
+        world.sleep_body(ball);
+        assert!(world.is_sleeping(ball));
+        world.update(&ball, |body| body.velocity.x = 1.0);
+        assert!(!world.is_sleeping(ball));
+    }
+
+    #[test]
+    fn it_wakes_sleeping_islands_when_gravity_changes() {
+        let (mut world, top) = stack(&Shape::Rect(Vec2::new(1.0, 1.0)), 3);
+        for _ in 0..120 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+        assert!(world.is_sleeping(top));
+        let y = world.get_body(top).unwrap().position.y;
+
+        world.gravity = Vec2::new(0.0, 10.0);
+        for _ in 0..30 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+        assert!(!world.is_sleeping(top));
+        assert!(world.get_body(top).unwrap().position.y > y + 0.5);
+    }
+
+    fn stack(shape: &Shape, height: u8) -> (PhysicsWorld<QuadTree<ArenaHandle>>, ArenaHandle) {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        world.add_body(Body {
+            shape: Shape::Rect(Vec2::new(40.0, 2.0)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+        let mut top = None;
+        for i in 0..height {
+            let mut body = Body {
+                position: Vec2::new(0.0, 1.5 + f32::from(i)),
+                shape: shape.clone(),
+                friction: 0.5,
+                static_friction: 0.6,
+                ..Body::default()
+            };
+            body.compute_inertia();
+            top = Some(world.add_body(body));
+        }
+        (world, top.unwrap())
+    }
+
+    #[test]
+    fn it_keeps_stacks_at_rest() {
+        for shape in &[
+            Shape::Rect(Vec2::new(1.0, 1.0)),
+            Shape::Circle(Circle::new(0.5)),
+        ] {
+            let (mut world, top) = stack(shape, 10);
+
+            for _ in 0..120 {
+                world.update_with_quad(1.0 / 60.0);
+            }
+
+            let body = world.get_body(top).unwrap();
+            assert!((body.position.y - 10.5).abs() < 0.1, "{:?}", body.position);
+            assert!(body.position.x.abs() < 0.01, "{:?}", body.position);
+            assert!(body.angle.abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn it_steps_with_a_fixed_timestep() {
+        let mut world = world();
+        world.integration_parameters_mut().set_timestep(0.1);
+        world.integration_parameters_mut().set_substeps(2);
+        let handle = world.add_body(Body {
+            velocity: Vec2::new(10.0, 0.0),
+            ..Body::default()
+        });
+
+        let update = world.update_fixed(0.15);
+        assert_eq!(update.steps, 1);
+        assert!((update.alpha - 0.5).abs() < 1e-4);
+        let body = world.get_body(handle).unwrap();
+        assert!((body.position.x - 1.0).abs() < 1e-4);
+        assert!((body.interpolated_position(update.alpha).x - 0.5).abs() < 1e-4);
+
+        // A long frame is cut off after the maximum number of steps
+        let update = world.update_fixed(10.0);
+        assert_eq!(update.steps, world.integration_parameters().max_steps());
+        assert!(update.alpha < 1.0);
+    }
+
+    #[test]
+    fn it_falls_with_substeps() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        world.integration_parameters_mut().set_substeps(4);
+        let handle = world.add_body(Body {
+            position: Vec2::new(0.0, 50.0),
+            ..Body::default()
+        });
+
+        for _ in 0..60 {
+            world.update_fixed(1.0 / 60.0);
+        }
+
+        // Falls about as far as the 5 units of a single step per frame
+        let body = world.get_body(handle).unwrap();
+        assert!((body.position.y - 45.0).abs() < 0.2, "{:?}", body.position);
+    }
+
+    #[test]
+    fn it_keeps_the_broad_phase_in_sync() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let handle = world.add_body(Body {
+            position: Vec2::new(0.0, 20.0),
+            shape: square(2.0),
+            ..Body::default()
+        });
+        let ground = world.add_body(Body {
+            shape: Shape::Rect(Vec2::new(20.0, 2.0)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+
+        let pair = CollisionPair {
+            a: handle,
+            b: ground,
+        }
+        .ordered();
+        let mut landed = false;
+        for _ in 0..300 {
+            landed |= world
+                .update_with_quad(1.0 / 60.0)
+                .iter()
+                .any(|collision| collision.pair == pair);
+        }
+
+        assert!(landed);
+        let body = world.get_body(handle).unwrap();
+        let mut collisions = HashSet::new();
+        world.broad_phase.check(body.get_aabb(), &mut collisions);
+        assert!(collisions.contains(&handle));
+        assert!(body.position.y < 3.0, "{:?}", body.position);
+    }
+
+    #[test]
+    fn it_moves_kinematic_bodies() {
+        let mut world = world();
+        let mut platform = Body {
+            shape: Shape::Rect(Vec2::new(2.0, 2.0)),
+            body_type: BodyType::KinematicVelocity,
+            ..Body::default()
+        };
+        platform.velocity = Vec2::new(3.0, 0.0);
+        let platform = world.add_body(platform);
+        let pushed = world.add_body(Body {
+            shape: square(1.0),
+            position: Vec2::new(1.6, 0.0),
+            ..Body::default()
+        });
+        let target = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.5)),
+            position: Vec2::new(0.0, 10.0),
+            body_type: BodyType::KinematicPosition,
+            kinematic_target: Some((Vec2::new(4.0, 10.0), 1.0)),
+            ..Body::default()
+        });
+
+        for _ in 0..60 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        // Contacts push dynamic bodies but never the kinematic one
+        let platform = world.get_body(platform).unwrap();
+        assert!((platform.position.x - 3.0).abs() < 1e-3);
+        assert!((platform.velocity.x - 3.0).abs() < 1e-6);
+        assert!(world.get_body(pushed).unwrap().position.x > platform.position.x + 1.4);
+
+        let target = world.get_body(target).unwrap();
+        assert!((target.position - Vec2::new(4.0, 10.0)).length() < 1e-3);
+        assert!((target.angle - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn it_keeps_kinematic_bodies_in_place_in_steps_without_length() {
+        let mut world = world();
+        let target = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.5)),
+            body_type: BodyType::KinematicPosition,
+            kinematic_target: Some((Vec2::new(4.0, 0.0), 1.0)),
+            ..Body::default()
+        });
+
+        world.update_with_quad(0.0);
+
+        let body = world.get_body(target).unwrap();
+        assert_eq!(body.position, Vec2::new(0.0, 0.0));
+        assert!(body.angle.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn it_ignores_joints_of_a_body_to_itself() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let body = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.5)),
+            ..Body::default()
+        });
+        world.add_joint(Joint::distance(
+            body,
+            body,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            1.0,
+        ));
+        world.add_joint(Joint::weld(
+            body,
+            body,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 0.0),
+        ));
+
+        for _ in 0..10 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        // Falls freely as if it had no joints
+        assert!(world.get_body(body).unwrap().velocity.y < 0.0);
+    }
+
+    #[test]
+    fn it_holds_bodies_together_with_joints() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        // Slow bodies aren't stopped, which would leave the spring off its rest
+        world
+            .integration_parameters_mut()
+            .set_rest_velocity_threshold(0.0);
+        let anchor = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.1)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+        // A rope of three links swinging from the anchor
+        let mut links = vec![];
+        let mut previous = anchor;
+        for x in [1.0, 2.0, 3.0] {
+            let link = world.add_body(Body {
+                shape: Shape::Circle(Circle::new(0.4)),
+                position: Vec2::new(x, 0.0),
+                ..Body::default()
+            });
+            world.add_joint(Joint::distance(
+                previous,
+                link,
+                Vec2::new(0.0, 0.0),
+                Vec2::new(0.0, 0.0),
+                1.0,
+            ));
+            links.push(link);
+            previous = link;
+        }
+        let mut bob = Body {
+            shape: Shape::Circle(Circle::new(0.5)),
+            position: Vec2::new(10.0, -3.0),
+            ..Body::default()
+        };
+        bob.set_mass(1.0);
+        let bob = world.add_body(bob);
+        let spring = world.add_joint(Joint::spring(
+            anchor,
+            bob,
+            Vec2::new(10.0, 0.0),
+            Vec2::new(0.0, 0.0),
+            2.0,
+            100.0,
+            5.0,
+        ));
+
+        let mut collided = false;
+        for _ in 0..600 {
+            collided |= !world.update_with_quad(1.0 / 60.0).is_empty();
+        }
+
+        // Connected links pass through each other instead of colliding
+        assert!(!collided);
+        let mut previous_position = Vec2::new(0.0, 0.0);
+        for link in &links {
+            let position = world.get_body(*link).unwrap().position;
+            assert!(((position - previous_position).length() - 1.0).abs() < 0.05);
+            previous_position = position;
+        }
+        // Hanging still, the spring is stretched by the weight of the bob
+        let bob_position = world.get_body(bob).unwrap().position;
+        assert!((bob_position.y + 2.1).abs() < 0.02, "{:?}", bob_position);
+
+        world.remove_body(&bob);
+        assert!(world.get_joint(spring).is_none());
+        world.remove_body(&links[0]);
+        assert!(world.joints_of(links[1]).len() == 1);
+    }
+
+    #[test]
+    fn it_holds_springs_without_stiffness_rigidly() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let anchor = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.1)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+        let bob = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.5)),
+            position: Vec2::new(0.0, -2.0),
+            ..Body::default()
+        });
+        world.add_joint(Joint::spring(
+            anchor,
+            bob,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 0.0),
+            2.0,
+            0.0,
+            0.0,
+        ));
+
+        world.update_with_quad(0.0);
+        for _ in 0..60 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        let position = world.get_body(bob).unwrap().position;
+        assert!((position.length() - 2.0).abs() < 0.05, "{:?}", position);
+    }
+
+    #[test]
+    fn it_limits_and_drives_revolute_joints() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let frame = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.1)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+        let door = world.add_body(Body {
+            shape: Shape::Rect(Vec2::new(2.0, 0.2)),
+            position: Vec2::new(1.0, 0.0),
+            ..Body::default()
+        });
+        let hinge = world.add_joint(
+            Joint::revolute(frame, door, Vec2::new(0.0, 0.0), Vec2::new(-1.0, 0.0))
+                .with_limits(-0.5, 0.5),
+        );
+
+        for _ in 0..120 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        // The door swings down until it hits the lower limit
+        let body = world.get_body(door).unwrap();
+        let hinge_position = body.position + Vec2::new(-1.0, 0.0).rotate(body.angle);
+        assert!(hinge_position.length() < 0.01, "{:?}", hinge_position);
+        assert!((body.angle + 0.5).abs() < 0.02, "{}", body.angle);
+
+        // Limits of a step without length don't push the door at all
+        world.update_with_quad(0.0);
+        assert!(world.get_body(door).unwrap().angular_velocity.is_finite());
+
+        world.update_joint(hinge, |joint| {
+            *joint = Joint::revolute(frame, door, Vec2::new(0.0, 0.0), Vec2::new(-1.0, 0.0))
+                .with_motor(1.0, 1000.0);
+        });
+        world.gravity = Vec2::new(0.0, 0.0);
+        for _ in 0..60 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+        assert!((world.get_body(door).unwrap().angular_velocity - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn it_slides_and_welds_bodies() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let base = world.add_body(Body {
+            shape: Shape::Circle(Circle::new(0.1)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+        let piston = world.add_body(Body {
+            shape: Shape::Rect(Vec2::new(1.0, 1.0)),
+            ..Body::default()
+        });
+        world.add_joint(
+            Joint::prismatic(
+                base,
+                piston,
+                Vec2::new(0.0, 0.0),
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+            )
+            .with_limits(0.0, 2.0)
+            .with_motor(4.0, 100.0),
+        );
+        // A box glued to the side of the piston moves along with it
+        let glued = world.add_body(Body {
+            shape: Shape::Rect(Vec2::new(1.0, 1.0)),
+            position: Vec2::new(0.0, 1.0),
+            ..Body::default()
+        });
+        world.add_joint(Joint::weld(
+            piston,
+            glued,
+            Vec2::new(0.0, 0.5),
+            Vec2::new(0.0, -0.5),
+        ));
+
+        for _ in 0..120 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        let piston = world.get_body(piston).unwrap();
+        assert!(
+            (piston.position - Vec2::new(2.0, 0.0)).length() < 0.02,
+            "{:?}",
+            piston.position
+        );
+        assert!(piston.angle.abs() < 0.01);
+        let glued = world.get_body(glued).unwrap();
+        assert!(
+            (glued.position - Vec2::new(2.0, 1.0)).length() < 0.02,
+            "{:?}",
+            glued.position
+        );
+        assert!(glued.angle.abs() < 0.01);
+    }
+
+    #[test]
+    fn it_drags_bodies_towards_targets() {
+        let mut world = world();
+        let mut body = Body {
+            shape: square(1.0),
+            ..Body::default()
+        };
+        body.set_mass(1.0);
+        let handle = world.add_body(body);
+        let drag = world.add_joint(Joint::target(
+            handle,
+            Vec2::new(0.5, 0.0),
+            Vec2::new(0.5, 0.0),
+            50.0,
+            10.0,
+            1000.0,
+        ));
+        world.update_joint(drag, |joint| joint.set_target(Vec2::new(5.5, 2.0)));
+
+        for _ in 0..180 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        let body = world.get_body(handle).unwrap();
+        let anchor = body.position + Vec2::new(0.5, 0.0).rotate(body.angle);
+        assert!(
+            (anchor - Vec2::new(5.5, 2.0)).length() < 0.05,
+            "{:?}",
+            anchor
+        );
+
+        // A weak joint can't lift a heavy body
+        world.remove_joint(drag);
+        world.gravity = Vec2::new(0.0, -10.0);
+        world.add_joint(Joint::target(
+            handle,
+            Vec2::new(0.0, 0.0),
+            Vec2::new(5.0, 10.0),
+            50.0,
+            10.0,
+            5.0,
+        ));
+        let start = world.get_body(handle).unwrap().position;
+        for _ in 0..60 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+        assert!(world.get_body(handle).unwrap().position.y < start.y);
+    }
+
+    #[test]
+    fn it_collides_compound_bodies() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        world.add_body(Body {
+            position: Vec2::new(0.0, -3.0),
+            shape: Shape::Rect(Vec2::new(10.0, 1.0)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+        // A circle standing on a box leg below it
+        let handle = world.add_body(Body {
+            position: Vec2::new(0.0, 0.5),
+            colliders: vec![ColliderPart::new(
+                Shape::Rect(Vec2::new(1.0, 1.0)),
+                Vec2::new(0.0, -2.0),
+                0.0,
+            )],
+            ..Body::default()
+        });
+
+        let mut touched = false;
+        for _ in 0..120 {
+            for collision in world.update_with_quad(1.0 / 60.0) {
+                let collider = if collision.pair.a == handle {
+                    collision.colliders.0
+                } else {
+                    collision.colliders.1
+                };
+                assert_eq!(collider, 1);
+                touched = true;
+            }
+        }
+
+        assert!(touched);
+        let body = world.get_body(handle).unwrap();
+        assert!(body.position.y > -0.1, "{:?}", body.position);
+        assert!(body.angle.abs() < 0.01);
+
+        let hit = world
+            .cast_ray(
+                Vec2::new(5.0, -2.0),
+                Vec2::new(-1.0, 0.0),
+                10.0,
+                |other, _| other == handle,
+            )
+            .unwrap();
+        assert_eq!(hit.collider, 1);
+    }
+
+    #[test]
+    fn it_reports_sensor_colliders() {
+        let mut world = world();
+        let mut sensor = ColliderPart::new(square(2.0), Vec2::new(3.0, 0.0), 0.0);
+        sensor.sensor = true;
+        let trigger = world.add_body(Body {
+            body_type: BodyType::Fixed,
+            colliders: vec![sensor],
+            ..Body::default()
+        });
+        let handle = world.add_body(Body {
+            position: Vec2::new(3.0, 2.5),
+            velocity: Vec2::new(0.0, -30.0),
+            linear_damping: Some(0.0),
+            ..Body::default()
+        });
+        let pair = CollisionPair {
+            a: handle,
+            b: trigger,
+        }
+        .ordered();
+        let colliders = if pair.a == handle { (0, 1) } else { (1, 0) };
+
+        let mut events = vec![];
+        for _ in 0..6 {
+            world.update_with_quad(1.0 / 60.0);
+            events.extend_from_slice(world.events());
+        }
+
+        assert_eq!(events[0], CollisionEvent::Started {
+            pair,
+            colliders,
+            sensor: true,
+        });
+        assert!(events.iter().all(|event| event.colliders() == colliders));
+        let body = world.get_body(handle).unwrap();
+        assert_eq!(body.velocity, Vec2::new(0.0, -30.0));
+    }
+
+    #[test]
+    fn it_keeps_mass_properties_consistent() {
+        let mut body = Body {
+            shape: Shape::Rect(Vec2::new(2.0, 2.0)),
+            colliders: vec![ColliderPart::new(
+                Shape::Rect(Vec2::new(2.0, 2.0)),
+                Vec2::new(2.0, 0.0),
+                0.0,
+            )],
+            ..Body::default()
+        };
+        body.set_density(0.5);
+        assert!((body.mass() - 4.0).abs() < 1e-5);
+        assert!((body.inv_mass() - 0.25).abs() < 1e-5);
+        // Two boxes of inertia 4 / 3 with the second one 2 away from the position
+        assert!((body.inertia() - (2.0 * 4.0 / 3.0 + 2.0 * 4.0)).abs() < 1e-5);
+        assert!((body.inv_inertia() * body.inertia() - 1.0).abs() < 1e-5);
+        // Halfway between the two boxes
+        assert!((body.center_of_mass() - Vec2::new(1.0, 0.0)).length() < 1e-5);
+
+        body.set_mass(8.0);
+        assert!((body.inv_mass() - 0.125).abs() < 1e-5);
+        assert!((body.inertia() - 2.0 * (2.0 * 4.0 / 3.0 + 2.0 * 4.0)).abs() < 1e-5);
+
+        for mass in [0.0, -1.0, f32::INFINITY, f32::NAN] {
+            body.set_mass(mass);
+            assert!(body.inv_mass().abs() < f32::EPSILON);
+            assert!(body.inv_inertia().abs() < f32::EPSILON);
+        }
+
+        // Segments have no area to take a density from
+        let mut segment = Body {
+            shape: Shape::Segment {
+                a: Vec2::new(-1.0, 0.0),
+                b: Vec2::new(1.0, 0.0),
+            },
+            ..Body::default()
+        };
+        segment.set_density(2.0);
+        assert!((segment.mass() - 1.0).abs() < 1e-5);
+        assert!(segment.center_of_mass().length() < 1e-5);
+        segment.colliders.push(ColliderPart::new(
+            segment.shape.clone(),
+            Vec2::new(0.0, 2.0),
+            0.0,
+        ));
+        segment.set_mass(2.0);
+        // Each segment takes half of the mass, the second one 2 away from the position
+        assert!((segment.inertia() - (2.0 / 3.0 + 4.0)).abs() < 1e-5);
+
+        // Default bodies take their mass from their default shape
+        let body = Body::default();
+        assert!((body.mass() - std::f32::consts::PI).abs() < 1e-5);
+        assert!((body.inertia() - std::f32::consts::PI / 2.0).abs() < 1e-5);
+        assert!(segment.inv_inertia() > 0.0);
+    }
+
+    #[test]
+    fn it_rests_bodies_without_mass_or_area_on_fixed_ones() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        world.add_body(Body {
+            position: Vec2::new(0.0, -1.0),
+            shape: Shape::Rect(Vec2::new(20.0, 2.0)),
+            body_type: BodyType::Fixed,
+            ..Body::default()
+        });
+        let mut weightless = Body {
+            position: Vec2::new(-3.0, 0.45),
+            shape: Shape::Rect(Vec2::new(1.0, 1.0)),
+            ..Body::default()
+        };
+        weightless.set_mass(0.0);
+        let weightless = world.add_body(weightless);
+        let mut segment = Body {
+            position: Vec2::new(3.0, 0.5),
+            shape: Shape::Segment {
+                a: Vec2::new(-1.0, 0.0),
+                b: Vec2::new(1.0, 0.0),
+            },
+            ..Body::default()
+        };
+        segment.set_density(1.0);
+        let segment = world.add_body(segment);
+
+        for _ in 0..60 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        let weightless = world.get_body(weightless).unwrap();
+        assert_eq!(weightless.position, Vec2::new(-3.0, 0.45));
+        assert!(weightless.angle.abs() < f32::EPSILON);
+        let segment = world.get_body(segment).unwrap();
+        assert!(segment.position.x.is_finite() && segment.angle.is_finite());
+        assert!(
+            segment.position.y > -0.1 && segment.position.y < 0.1,
+            "{:?}",
+            segment.position
+        );
+    }
+
+    #[test]
+    fn it_does_not_move_bodies_with_infinite_mass() {
+        let mut world = world();
+        world.gravity = Vec2::new(0.0, -10.0);
+        let mut wall = Body::default();
+        wall.set_mass(f32::INFINITY);
+        let wall = world.add_body(wall);
+        let handle = world.add_body(Body {
+            position: Vec2::new(-2.5, 0.0),
+            velocity: Vec2::new(10.0, 0.0),
+            gravity_scale: 0.0,
+            ..Body::default()
+        });
+
+        for _ in 0..30 {
+            world.update_with_quad(1.0 / 60.0);
+        }
+
+        let wall = world.get_body(wall).unwrap();
+        assert_eq!(wall.position, Vec2::new(0.0, 0.0));
+        assert_eq!(wall.velocity, Vec2::new(0.0, 0.0));
+        let body = world.get_body(handle).unwrap();
+        assert!(body.position.x.is_finite() && body.position.x < -1.9);
+        assert!(body.velocity.x <= 0.0);
+    }
+}
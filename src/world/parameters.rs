@@ -0,0 +1,221 @@
+#[cfg(feature = "bevy")]
+use bevy::prelude::Resource;
+
+/**
+ * Tuning values of the solver and the integration of a `PhysicsWorld`
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+pub struct IntegrationParameters {
+    position_correction:     f32,
+    allowed_penetration:     f32,
+    rest_velocity_threshold: f32,
+    default_linear_damping:  f32,
+    default_angular_damping: f32,
+    velocity_iterations:     usize,
+    position_iterations:     usize,
+    timestep:                f32,
+    max_steps:               usize,
+    substeps:                usize,
+    sleep_linear_threshold:  f32,
+    sleep_angular_threshold: f32,
+    time_to_sleep:           f32,
+    target_angular_damping:  f32,
+}
+
+impl Default for IntegrationParameters {
+    fn default() -> Self {
+        Self {
+            position_correction:     0.5,
+            allowed_penetration:     0.01,
+            rest_velocity_threshold: 0.1,
+            default_linear_damping:  5.0,
+            default_angular_damping: 5.0,
+            velocity_iterations:     8,
+            position_iterations:     3,
+            timestep:                1.0 / 60.0,
+            max_steps:               4,
+            substeps:                1,
+            sleep_linear_threshold:  0.2,
+            sleep_angular_threshold: 0.2,
+            time_to_sleep:           0.5,
+            target_angular_damping:  1.2,
+        }
+    }
+}
+
+impl IntegrationParameters {
+    /**
+     * Fraction of the penetration resolved every step,
+     * a high percentage means no penetration but more jitter
+     */
+    #[must_use]
+    pub fn position_correction(&self) -> f32 {
+        self.position_correction
+    }
+
+    pub fn set_position_correction(&mut self, position_correction: f32) {
+        self.position_correction = position_correction.clamp(0f32, 1f32);
+    }
+
+    /**
+     * Penetration depth that is left uncorrected,
+     * allows resting contacts without jittering
+     */
+    #[must_use]
+    pub fn allowed_penetration(&self) -> f32 {
+        self.allowed_penetration
+    }
+
+    pub fn set_allowed_penetration(&mut self, allowed_penetration: f32) {
+        self.allowed_penetration = allowed_penetration.max(0f32);
+    }
+
+    /**
+     * Speed along both axes below which a body touching another body is brought to rest
+     */
+    #[must_use]
+    pub fn rest_velocity_threshold(&self) -> f32 {
+        self.rest_velocity_threshold
+    }
+
+    pub fn set_rest_velocity_threshold(&mut self, rest_velocity_threshold: f32) {
+        self.rest_velocity_threshold = rest_velocity_threshold.max(0f32);
+    }
+
+    /**
+     * Linear damping of bodies that don't set their own
+     */
+    #[must_use]
+    pub fn default_linear_damping(&self) -> f32 {
+        self.default_linear_damping
+    }
+
+    pub fn set_default_linear_damping(&mut self, default_linear_damping: f32) {
+        self.default_linear_damping = default_linear_damping.max(0f32);
+    }
+
+    /**
+     * Angular damping of bodies that don't set their own
+     */
+    #[must_use]
+    pub fn default_angular_damping(&self) -> f32 {
+        self.default_angular_damping
+    }
+
+    pub fn set_default_angular_damping(&mut self, default_angular_damping: f32) {
+        self.default_angular_damping = default_angular_damping.max(0f32);
+    }
+
+    /**
+     * Number of times the contact velocities are solved every step,
+     * more iterations make stacks more stable
+     */
+    #[must_use]
+    pub fn velocity_iterations(&self) -> usize {
+        self.velocity_iterations
+    }
+
+    pub fn set_velocity_iterations(&mut self, velocity_iterations: usize) {
+        self.velocity_iterations = velocity_iterations;
+    }
+
+    /**
+     * Number of times the penetration is corrected every step
+     */
+    #[must_use]
+    pub fn position_iterations(&self) -> usize {
+        self.position_iterations
+    }
+
+    pub fn set_position_iterations(&mut self, position_iterations: usize) {
+        self.position_iterations = position_iterations;
+    }
+
+    /**
+     * Length of a fixed step in seconds
+     */
+    #[must_use]
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    pub fn set_timestep(&mut self, timestep: f32) {
+        self.timestep = timestep.max(f32::EPSILON);
+    }
+
+    /**
+     * Maximum number of fixed steps taken in a single update,
+     * time beyond that is dropped to catch up after a slow frame
+     */
+    #[must_use]
+    pub fn max_steps(&self) -> usize {
+        self.max_steps
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.max_steps = max_steps;
+    }
+
+    /**
+     * Number of smaller steps every fixed step is divided into
+     */
+    #[must_use]
+    pub fn substeps(&self) -> usize {
+        self.substeps
+    }
+
+    pub fn set_substeps(&mut self, substeps: usize) {
+        self.substeps = substeps.max(1);
+    }
+
+    /**
+     * Speed below which a body counts as resting
+     */
+    #[must_use]
+    pub fn sleep_linear_threshold(&self) -> f32 {
+        self.sleep_linear_threshold
+    }
+
+    pub fn set_sleep_linear_threshold(&mut self, sleep_linear_threshold: f32) {
+        self.sleep_linear_threshold = sleep_linear_threshold.max(0f32);
+    }
+
+    /**
+     * Angular speed in radians per second below which a body counts as resting
+     */
+    #[must_use]
+    pub fn sleep_angular_threshold(&self) -> f32 {
+        self.sleep_angular_threshold
+    }
+
+    pub fn set_sleep_angular_threshold(&mut self, sleep_angular_threshold: f32) {
+        self.sleep_angular_threshold = sleep_angular_threshold.max(0f32);
+    }
+
+    /**
+     * Seconds every body of an island has to rest before the island sleeps,
+     * infinity disables sleeping
+     */
+    #[must_use]
+    pub fn time_to_sleep(&self) -> f32 {
+        self.time_to_sleep
+    }
+
+    pub fn set_time_to_sleep(&mut self, time_to_sleep: f32) {
+        self.time_to_sleep = time_to_sleep.max(0f32);
+    }
+
+    /**
+     * Fraction of the angular velocity removed per second from bodies dragged
+     * by a target joint, otherwise they keep spinning around the anchor
+     */
+    #[must_use]
+    pub fn target_angular_damping(&self) -> f32 {
+        self.target_angular_damping
+    }
+
+    pub fn set_target_angular_damping(&mut self, target_angular_damping: f32) {
+        self.target_angular_damping = target_angular_damping.max(0f32);
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+
+use super::{broad::BroadPhase, ArenaHandle, PhysicsWorld};
+use crate::{
+    body::Body,
+    checks::{point_in_shape, ray_vs_shape, shape_vs_shape, sweep_shape_vs_shape},
+    shape::{enclosing_aabb, Shape, AABB},
+    Vec2,
+};
+
+/**
+ * A body hit by a ray
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub handle:   ArenaHandle,
+    /**
+     * Collider of the body that was hit, see `Collision::colliders`
+     */
+    pub collider: usize,
+    /**
+     * Time of impact, the distance along the ray
+     * in multiples of its direction
+     */
+    pub toi:      f32,
+    pub point:    Vec2<f32>,
+    /**
+     * Surface normal of the body at the hit
+     */
+    pub normal:   Vec2<f32>,
+}
+
+/**
+ * A body hit by a swept shape
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeHit {
+    pub handle:   ArenaHandle,
+    pub collider: usize,
+    /**
+     * Fraction of the translation the shape moved before the hit
+     */
+    pub toi:      f32,
+    /**
+     * Surface normal of the body at the hit, pointing towards the shape
+     */
+    pub normal:   Vec2<f32>,
+}
+
+impl<Broad> PhysicsWorld<Broad>
+where
+    Broad: BroadPhase<ArenaHandle> + std::fmt::Debug,
+{
+    /**
+     * First body hit by a ray within `max_toi` multiples of `direction`,
+     * bodies are skipped when `filter` returns false
+     */
+    pub fn cast_ray<F>(
+        &self,
+        origin: Vec2<f32>,
+        direction: Vec2<f32>,
+        max_toi: f32,
+        filter: F,
+    ) -> Option<RayHit>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool, {
+        self.cast_ray_all(origin, direction, max_toi, filter)
+            .into_iter()
+            .next()
+    }
+
+    /**
+     * Every body hit by a ray, ordered by their time of impact
+     */
+    pub fn cast_ray_all<F>(
+        &self,
+        origin: Vec2<f32>,
+        direction: Vec2<f32>,
+        max_toi: f32,
+        mut filter: F,
+    ) -> Vec<RayHit>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool, {
+        let mut candidates = HashSet::new();
+        self.broad_phase
+            .check_ray(origin, direction, max_toi, &mut candidates);
+
+        let mut hits: Vec<RayHit> = candidates
+            .into_iter()
+            .filter_map(|handle| {
+                let body = self.bodies.get(handle)?;
+                if !filter(handle, body) {
+                    return None;
+                }
+                let (collider, intersection) = body
+                    .placed_colliders()
+                    .filter_map(|collider| {
+                        ray_vs_shape(
+                            origin,
+                            direction,
+                            max_toi,
+                            collider.shape,
+                            collider.position,
+                            collider.angle,
+                        )
+                        .map(|intersection| (collider.index, intersection))
+                    })
+                    .min_by(|(_, a), (_, b)| a.toi.total_cmp(&b.toi))?;
+                Some(RayHit {
+                    handle,
+                    collider,
+                    toi: intersection.toi,
+                    point: origin + direction * intersection.toi,
+                    normal: intersection.normal,
+                })
+            })
+            .collect();
+        hits.sort_unstable_by(|a, b| a.toi.total_cmp(&b.toi).then(a.handle.cmp(&b.handle)));
+        hits
+    }
+
+    /**
+     * First body hit by a shape moved from `start` along `translation`,
+     * bodies are skipped when `filter` returns false
+     */
+    pub fn cast_shape<F>(
+        &self,
+        shape: &Shape,
+        start: Vec2<f32>,
+        translation: Vec2<f32>,
+        filter: F,
+    ) -> Option<ShapeHit>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool, {
+        self.cast_rotated_shape(shape, start, 0f32, translation, true, filter)
+    }
+
+    /**
+     * Same as `cast_shape` with the shape rotated by an angle,
+     * sensor colliders are only hit when `sensors` is set
+     */
+    pub(super) fn cast_rotated_shape<F>(
+        &self,
+        shape: &Shape,
+        start: Vec2<f32>,
+        angle: f32,
+        translation: Vec2<f32>,
+        sensors: bool,
+        mut filter: F,
+    ) -> Option<ShapeHit>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool, {
+        let start_aabb = shape.get_aabb(start, angle);
+        let end_aabb = shape.get_aabb(start + translation, angle);
+        let swept_aabb = AABB {
+            min: Vec2::new(
+                start_aabb.min.x.min(end_aabb.min.x),
+                start_aabb.min.y.min(end_aabb.min.y),
+            ),
+            max: Vec2::new(
+                start_aabb.max.x.max(end_aabb.max.x),
+                start_aabb.max.y.max(end_aabb.max.y),
+            ),
+        };
+        let mut candidates = HashSet::new();
+        self.broad_phase.check(swept_aabb, &mut candidates);
+
+        candidates
+            .into_iter()
+            .filter_map(|handle| {
+                let body = self.bodies.get(handle)?;
+                if !filter(handle, body) {
+                    return None;
+                }
+                body.placed_colliders()
+                    .filter(|collider| sensors || !collider.sensor)
+                    .filter_map(|collider| {
+                        let intersection = sweep_shape_vs_shape(
+                            shape,
+                            collider.shape,
+                            start,
+                            collider.position,
+                            angle,
+                            collider.angle,
+                            translation,
+                        )?;
+                        Some(ShapeHit {
+                            handle,
+                            collider: collider.index,
+                            toi: intersection.toi,
+                            normal: intersection.normal,
+                        })
+                    })
+                    .min_by(|a, b| a.toi.total_cmp(&b.toi))
+            })
+            .min_by(|a, b| a.toi.total_cmp(&b.toi).then(a.handle.cmp(&b.handle)))
+    }
+
+    /**
+     * Broad phase candidates overlapping a bounding box that pass `filter`
+     * and the exact `check`, ordered by their handles
+     */
+    fn query<F, C>(&self, aabb: AABB<i32>, mut filter: F, mut check: C) -> Vec<ArenaHandle>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool,
+        C: FnMut(&Body) -> bool, {
+        let mut candidates = HashSet::new();
+        self.broad_phase.check(aabb, &mut candidates);
+
+        let mut handles: Vec<ArenaHandle> = candidates
+            .into_iter()
+            .filter(|handle| {
+                self.bodies
+                    .get(*handle)
+                    .is_some_and(|body| filter(*handle, body) && check(body))
+            })
+            .collect();
+        handles.sort_unstable();
+        handles
+    }
+
+    /**
+     * Bodies with a collider that contains a point
+     */
+    pub fn bodies_at_point<F>(&self, point: Vec2<f32>, filter: F) -> Vec<ArenaHandle>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool, {
+        self.query(enclosing_aabb(point, point), filter, |body| {
+            body.placed_colliders().any(|collider| {
+                point_in_shape(point, collider.shape, collider.position, collider.angle)
+            })
+        })
+    }
+
+    /**
+     * Bodies overlapping a shape at a position
+     */
+    pub fn intersect_shape<F>(
+        &self,
+        shape: &Shape,
+        position: Vec2<f32>,
+        filter: F,
+    ) -> Vec<ArenaHandle>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool, {
+        self.query(shape.get_aabb(position, 0f32), filter, |body| {
+            body.placed_colliders().any(|collider| {
+                shape_vs_shape(
+                    shape,
+                    collider.shape,
+                    position,
+                    collider.position,
+                    0f32,
+                    collider.angle,
+                )
+                .is_some()
+            })
+        })
+    }
+
+    /**
+     * Bodies overlapping an axis aligned box
+     */
+    pub fn bodies_in_aabb<F>(&self, aabb: AABB<f32>, filter: F) -> Vec<ArenaHandle>
+    where
+        F: FnMut(ArenaHandle, &Body) -> bool, {
+        self.intersect_shape(
+            &Shape::Rect(aabb.max - aabb.min),
+            (aabb.min + aabb.max) / 2f32,
+            filter,
+        )
+    }
+}
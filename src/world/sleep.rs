@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use super::{broad::BroadPhase, ArenaHandle, PhysicsWorld};
+use crate::{body::Body, collision::Collision, Vec2};
+
+/**
+ * Bodies that are simulated, fixed and sleeping bodies are not
+ */
+pub(super) fn is_active(body: &Body) -> bool {
+    !body.body_type.is_fixed() && !body.sleeping
+}
+
+/**
+ * Bodies that can sleep and join islands, kinematic bodies
+ * are moved by hand and connect islands as little as fixed ones do
+ */
+fn is_awake_dynamic(body: &Body) -> bool {
+    body.body_type.is_dynamic() && !body.sleeping
+}
+
+/**
+ * Active bodies that wake up sleeping bodies they touch,
+ * resting kinematic bodies let them sleep
+ */
+fn wakes_others(body: &Body) -> bool {
+    is_active(body)
+        && (body.body_type.is_dynamic()
+            || body.velocity != Vec2::new(0f32, 0f32)
+            || body.angular_velocity != 0f32)
+}
+
+/**
+ * Root of the set a body belongs to while building islands
+ */
+fn find_root(parents: &mut HashMap<ArenaHandle, ArenaHandle>, handle: ArenaHandle) -> ArenaHandle {
+    let mut root = handle;
+    while let Some(parent) = parents.get(&root).copied().filter(|parent| *parent != root) {
+        root = parent;
+    }
+    parents.insert(handle, root);
+    root
+}
+
+impl<Broad> PhysicsWorld<Broad>
+where
+    Broad: BroadPhase<ArenaHandle> + std::fmt::Debug,
+{
+    #[must_use]
+    pub fn is_sleeping(&self, handle: ArenaHandle) -> bool {
+        self.bodies.get(handle).is_some_and(|body| body.sleeping)
+    }
+
+    /**
+     * Wakes a body together with every body of the island it sleeps in
+     */
+    pub fn wake_body(&mut self, handle: ArenaHandle) {
+        if let Some(body) = self.bodies.get_mut(handle) {
+            body.sleeping = false;
+            body.sleep_time = 0f32;
+        }
+
+        let members = self
+            .sleeping_islands
+            .remove(&handle)
+            .and_then(|island| self.islands.remove(&island))
+            .unwrap_or_default();
+        for member in members {
+            self.sleeping_islands.remove(&member);
+            if let Some(body) = self.bodies.get_mut(member) {
+                body.sleeping = false;
+                body.sleep_time = 0f32;
+            }
+        }
+    }
+
+    /**
+     * Wakes every sleeping body
+     */
+    pub fn wake_all(&mut self) {
+        let handles: Vec<ArenaHandle> = self.sleeping_islands.keys().copied().collect();
+        for handle in handles {
+            self.wake_body(handle);
+        }
+    }
+
+    /**
+     * Wakes every sleeping body when the gravity changed since the last step,
+     * they would otherwise keep resting against the old one
+     */
+    pub(super) fn wake_on_gravity_change(&mut self) {
+        if self.gravity != self.stepped_gravity {
+            self.stepped_gravity = self.gravity;
+            self.wake_all();
+        }
+    }
+
+    /**
+     * Puts a body to sleep on its own, it wakes up on contact
+     */
+    pub fn sleep_body(&mut self, handle: ArenaHandle) {
+        if self.bodies.get(handle).is_some_and(is_awake_dynamic) {
+            self.put_to_sleep(&[handle]);
+        }
+    }
+
+    fn put_to_sleep(&mut self, handles: &[ArenaHandle]) {
+        let island = self.next_island;
+        self.next_island += 1;
+
+        for handle in handles {
+            if let Some(body) = self.bodies.get_mut(*handle) {
+                body.sleeping = true;
+                body.velocity = Vec2::new(0f32, 0f32);
+                body.angular_velocity = 0f32;
+                self.sleeping_islands.insert(*handle, island);
+                self.islands.entry(island).or_default().push(*handle);
+            }
+        }
+    }
+
+    /**
+     * Wakes the islands of sleeping bodies touched by or jointed to active ones,
+     * returns whether any body woke up
+     */
+    pub(super) fn wake_touched(&mut self, collisions: &[Collision<f32, ArenaHandle>]) -> bool {
+        let touched: Vec<ArenaHandle> = collisions
+            .iter()
+            .filter(|collision| !collision.sensor)
+            .map(|collision| (collision.pair.a, collision.pair.b))
+            .chain(self.joints.iter().map(|(_, joint)| (joint.a, joint.b)))
+            .filter_map(|(a_handle, b_handle)| {
+                let a = self.bodies.get(a_handle)?;
+                let b = self.bodies.get(b_handle)?;
+                if a.sleeping && wakes_others(b) {
+                    Some(a_handle)
+                } else if b.sleeping && wakes_others(a) {
+                    Some(b_handle)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        for handle in &touched {
+            self.wake_body(*handle);
+        }
+        !touched.is_empty()
+    }
+
+    /**
+     * Groups the bodies touching or jointed to each other into islands,
+     * islands that have been resting long enough go to sleep together.
+     * Fixed and kinematic bodies don't join islands so that everything
+     * resting on the same ground doesn't have to sleep at once
+     */
+    pub(super) fn update_islands(&mut self, collisions: &[Collision<f32, ArenaHandle>], dt: f32) {
+        let linear_threshold = self.parameters.sleep_linear_threshold();
+        let angular_threshold = self.parameters.sleep_angular_threshold();
+        for (_, body) in &mut self.bodies {
+            if !is_awake_dynamic(body) {
+                continue;
+            }
+            if body.velocity.length() < linear_threshold
+                && body.angular_velocity.abs() < angular_threshold
+            {
+                body.sleep_time += dt;
+            } else {
+                body.sleep_time = 0f32;
+            }
+        }
+
+        let mut parents = HashMap::new();
+        let connections = collisions
+            .iter()
+            .filter(|collision| !collision.sensor)
+            .map(|collision| (collision.pair.a, collision.pair.b))
+            .chain(self.joints.iter().map(|(_, joint)| (joint.a, joint.b)));
+        for (a, b) in connections {
+            if self.bodies.get(a).is_some_and(is_awake_dynamic)
+                && self.bodies.get(b).is_some_and(is_awake_dynamic)
+            {
+                let a_root = find_root(&mut parents, a);
+                let b_root = find_root(&mut parents, b);
+                parents.insert(a_root, b_root);
+            }
+        }
+
+        let mut islands: HashMap<ArenaHandle, (Vec<ArenaHandle>, bool)> = HashMap::new();
+        for (handle, body) in &self.bodies {
+            if !is_awake_dynamic(body) {
+                continue;
+            }
+            let (members, resting) = islands
+                .entry(find_root(&mut parents, handle))
+                .or_insert_with(|| (vec![], true));
+            members.push(handle);
+            *resting &= body.sleep_time >= self.parameters.time_to_sleep();
+        }
+
+        for (members, resting) in islands.into_values() {
+            if resting {
+                self.put_to_sleep(&members);
+            }
+        }
+    }
+}
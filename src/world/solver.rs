@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use generational_arena::Arena;
+
+use super::{ArenaHandle, IntegrationParameters};
+use crate::{
+    body::Body,
+    collision::{Collision, CollisionPair},
+    Vec2,
+};
+
+/**
+ * Inverse mass and inverse inertia of a body,
+ * fixed and kinematic bodies are treated as having infinite mass
+ */
+pub(super) fn inverse_mass(body: &Body) -> (f32, f32) {
+    if body.body_type.is_dynamic() {
+        (body.inv_mass, body.inv_inertia)
+    } else {
+        (0f32, 0f32)
+    }
+}
+
+/**
+ * Velocity of b relative to a at the given offsets from their positions
+ */
+pub(super) fn relative_velocity(
+    a: &Body,
+    b: &Body,
+    a_offset: Vec2<f32>,
+    b_offset: Vec2<f32>,
+) -> Vec2<f32> {
+    (b.velocity + b_offset.perpendicular() * b.angular_velocity)
+        - (a.velocity + a_offset.perpendicular() * a.angular_velocity)
+}
+
+/**
+ * Inverse of the mass felt by an impulse along `direction` at the given offsets
+ */
+fn effective_inv_mass(
+    a: &Body,
+    b: &Body,
+    a_offset: Vec2<f32>,
+    b_offset: Vec2<f32>,
+    direction: Vec2<f32>,
+) -> f32 {
+    let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+    let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+    let a_offset_direction = a_offset.cross(&direction);
+    let b_offset_direction = b_offset.cross(&direction);
+    a_inv_mass
+        + b_inv_mass
+        + a_offset_direction * a_offset_direction * a_inv_inertia
+        + b_offset_direction * b_offset_direction * b_inv_inertia
+}
+
+/**
+ * Mass felt by an impulse along `direction` at the given offsets,
+ * 0 when neither body can be moved by it
+ */
+pub(super) fn effective_mass(
+    a: &Body,
+    b: &Body,
+    a_offset: Vec2<f32>,
+    b_offset: Vec2<f32>,
+    direction: Vec2<f32>,
+) -> f32 {
+    let inv_mass = effective_inv_mass(a, b, a_offset, b_offset, direction);
+    if inv_mass > 0f32 {
+        1f32 / inv_mass
+    } else {
+        0f32
+    }
+}
+
+/**
+ * Whether impulses can move or rotate a body
+ */
+fn is_movable(body: &Body) -> bool {
+    inverse_mass(body) != (0f32, 0f32)
+}
+
+/**
+ * Applies an impulse to b at `b_offset` and the opposite impulse to a at `a_offset`
+ */
+pub(super) fn apply_impulse(
+    a: &mut Body,
+    b: &mut Body,
+    a_offset: Vec2<f32>,
+    b_offset: Vec2<f32>,
+    impulse: Vec2<f32>,
+) {
+    let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+    let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+
+    a.velocity -= impulse * a_inv_mass;
+    a.angular_velocity -= a_offset.cross(&impulse) * a_inv_inertia;
+    b.velocity += impulse * b_inv_mass;
+    b.angular_velocity += b_offset.cross(&impulse) * b_inv_inertia;
+}
+
+/**
+ * Impulses accumulated on a contact point during a step,
+ * used to warm start the solver when the same features touch in the next step
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct CachedImpulse {
+    feature:         u32,
+    normal_impulse:  f32,
+    tangent_impulse: f32,
+}
+
+pub type ContactCache = HashMap<(CollisionPair<ArenaHandle>, (usize, usize)), Vec<CachedImpulse>>;
+
+#[derive(Debug)]
+struct PointConstraint {
+    /**
+     * Contact point relative to the bodies in their local space,
+     * used to track the penetration while positions are corrected
+     */
+    a_anchor:          Vec2<f32>,
+    b_anchor:          Vec2<f32>,
+    /**
+     * Contact point relative to the bodies in world space
+     */
+    a_offset:          Vec2<f32>,
+    b_offset:          Vec2<f32>,
+    penetration_depth: f32,
+    normal_mass:       f32,
+    tangent_mass:      f32,
+    /**
+     * Normal velocity the bodies should separate with after the solve
+     */
+    velocity_bias:     f32,
+    normal_impulse:    f32,
+    tangent_impulse:   f32,
+    feature:           u32,
+}
+
+/**
+ * The contact between two bodies prepared for the sequential impulse solver
+ */
+#[derive(Debug)]
+pub struct ContactConstraint {
+    pub pair:         CollisionPair<ArenaHandle>,
+    pub colliders:    (usize, usize),
+    normal:           Vec2<f32>,
+    points:           Vec<PointConstraint>,
+    static_friction:  f32,
+    dynamic_friction: f32,
+}
+
+impl ContactConstraint {
+    /**
+     * Prepares a collision for solving,
+     * returns `None` if neither body can be moved or the collision is with a sensor
+     */
+    pub fn new(
+        bodies: &Arena<Body>,
+        collision: &Collision<f32, ArenaHandle>,
+        cache: &ContactCache,
+        parameters: &IntegrationParameters,
+    ) -> Option<Self> {
+        let a = bodies.get(collision.pair.a)?;
+        let b = bodies.get(collision.pair.b)?;
+        if collision.sensor || (!is_movable(a) && !is_movable(b)) {
+            return None;
+        }
+
+        let a_collider = a.placed_collider(collision.colliders.0)?;
+        let b_collider = b.placed_collider(collision.colliders.1)?;
+        let normal = collision.manifold.normal;
+        let tangent = normal.perpendicular();
+        let restitution = a_collider.restitution.min(b_collider.restitution);
+        let cached = cache.get(&(collision.pair, collision.colliders));
+
+        let points = collision
+            .manifold
+            .points()
+            .iter()
+            .map(|point| {
+                let a_offset = point.position - a.position;
+                let b_offset = point.position - b.position;
+
+                let velocity_along_normal =
+                    relative_velocity(a, b, a_offset, b_offset).dot(&normal);
+                // Slow contacts don't bounce, which lets bodies come to rest
+                let velocity_bias = if velocity_along_normal < -parameters.rest_velocity_threshold()
+                {
+                    -restitution * velocity_along_normal
+                } else {
+                    0f32
+                };
+
+                let (normal_impulse, tangent_impulse) = cached
+                    .and_then(|impulses| {
+                        impulses
+                            .iter()
+                            .find(|impulse| impulse.feature == point.feature)
+                    })
+                    .map_or((0f32, 0f32), |impulse| {
+                        (impulse.normal_impulse, impulse.tangent_impulse)
+                    });
+
+                PointConstraint {
+                    a_anchor: a_offset.rotate(-a.angle),
+                    b_anchor: b_offset.rotate(-b.angle),
+                    a_offset,
+                    b_offset,
+                    penetration_depth: point.penetration_depth,
+                    normal_mass: effective_mass(a, b, a_offset, b_offset, normal),
+                    tangent_mass: effective_mass(a, b, a_offset, b_offset, tangent),
+                    velocity_bias,
+                    normal_impulse,
+                    tangent_impulse,
+                    feature: point.feature,
+                }
+            })
+            .collect();
+
+        Some(Self {
+            pair: collision.pair,
+            colliders: collision.colliders,
+            normal,
+            points,
+            static_friction: (a_collider.static_friction * b_collider.static_friction).sqrt(),
+            dynamic_friction: (a_collider.friction * b_collider.friction).sqrt(),
+        })
+    }
+
+    /**
+     * Applies the impulses cached from the previous step
+     */
+    pub fn warm_start(&self, bodies: &mut Arena<Body>) {
+        let (a, b) = bodies.get2_mut(self.pair.a, self.pair.b);
+        let (a, b) = (a.unwrap(), b.unwrap());
+        let tangent = self.normal.perpendicular();
+
+        for point in &self.points {
+            let impulse = self.normal * point.normal_impulse + tangent * point.tangent_impulse;
+            apply_impulse(a, b, point.a_offset, point.b_offset, impulse);
+        }
+    }
+
+    /**
+     * Runs a single velocity iteration,
+     * the accumulated impulses are clamped instead of every single impulse
+     */
+    pub fn solve_velocity(&mut self, bodies: &mut Arena<Body>) {
+        let (a, b) = bodies.get2_mut(self.pair.a, self.pair.b);
+        let (a, b) = (a.unwrap(), b.unwrap());
+        let tangent = self.normal.perpendicular();
+
+        // Friction is solved first as the normal impulse is more important
+        for point in &mut self.points {
+            let velocity_along_tangent =
+                relative_velocity(a, b, point.a_offset, point.b_offset).dot(&tangent);
+            let impulse = point.tangent_impulse - velocity_along_tangent * point.tangent_mass;
+
+            // Coulomb's law, resting contacts hold as long as they stay within
+            // the static cone of the normal impulse, sliding ones use the dynamic one
+            let impulse = if impulse.abs() <= self.static_friction * point.normal_impulse {
+                impulse
+            } else {
+                let max_friction = self.dynamic_friction * point.normal_impulse;
+                impulse.clamp(-max_friction, max_friction)
+            };
+            let change = impulse - point.tangent_impulse;
+            point.tangent_impulse = impulse;
+
+            apply_impulse(a, b, point.a_offset, point.b_offset, tangent * change);
+        }
+
+        for point in &mut self.points {
+            let velocity_along_normal =
+                relative_velocity(a, b, point.a_offset, point.b_offset).dot(&self.normal);
+            let impulse = (point.normal_impulse
+                - (velocity_along_normal - point.velocity_bias) * point.normal_mass)
+                .max(0f32);
+            let change = impulse - point.normal_impulse;
+            point.normal_impulse = impulse;
+
+            apply_impulse(a, b, point.a_offset, point.b_offset, self.normal * change);
+        }
+    }
+
+    /**
+     * Runs a single position iteration, pushing the bodies apart
+     * by a part of their remaining penetration
+     */
+    pub fn solve_position(&self, bodies: &mut Arena<Body>, parameters: &IntegrationParameters) {
+        let (a, b) = bodies.get2_mut(self.pair.a, self.pair.b);
+        let (a, b) = (a.unwrap(), b.unwrap());
+        let (a_inv_mass, a_inv_inertia) = inverse_mass(a);
+        let (b_inv_mass, b_inv_inertia) = inverse_mass(b);
+
+        for point in &self.points {
+            let a_offset = point.a_anchor.rotate(a.angle);
+            let b_offset = point.b_anchor.rotate(b.angle);
+            // The anchors started out at the same point,
+            // moving them apart along the normal reduces the penetration
+            let penetration_depth = point.penetration_depth
+                - ((b.position + b_offset) - (a.position + a_offset)).dot(&self.normal);
+
+            let correction = (penetration_depth - parameters.allowed_penetration()).max(0f32)
+                * parameters.position_correction();
+            if correction == 0f32 {
+                continue;
+            }
+
+            let correction =
+                self.normal * (correction * effective_mass(a, b, a_offset, b_offset, self.normal));
+
+            a.position -= correction * a_inv_mass;
+            a.angle -= a_offset.cross(&correction) * a_inv_inertia;
+            b.position += correction * b_inv_mass;
+            b.angle += b_offset.cross(&correction) * b_inv_inertia;
+        }
+    }
+
+    /**
+     * Impulses accumulated during the step, to be cached for the next one
+     */
+    #[must_use]
+    pub fn cached_impulses(&self) -> Vec<CachedImpulse> {
+        self.points
+            .iter()
+            .map(|point| CachedImpulse {
+                feature:         point.feature,
+                normal_impulse:  point.normal_impulse,
+                tangent_impulse: point.tangent_impulse,
+            })
+            .collect()
+    }
+}